@@ -0,0 +1,20 @@
+//! Prints the hand type distribution for the day7 rule sets.
+//! Any hands passed as arguments get their best joker substitution reported.
+use aoc2023::day7::{best_substitution, type_distribution, Card, Hand, Rules};
+
+fn main() {
+    let rules = [("standard", Rules::standard()), ("jokers", Rules::jokers())];
+    for (name, rules) in &rules {
+        println!("{name}:");
+        println!("{}", type_distribution(rules));
+    }
+
+    for arg in std::env::args().skip(1) {
+        let hand: Hand = arg.chars().map(Card::from).collect();
+        for (name, rules) in &rules {
+            let (best, hand_type) = best_substitution(rules, &hand);
+            let best: String = best.into_iter().map(char::from).collect();
+            println!("{arg} ({name}): {best} {hand_type:?}");
+        }
+    }
+}
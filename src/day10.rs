@@ -1,123 +1,354 @@
-fn idx(x: usize, y: usize, width: usize) -> usize {
-    x + y * width
-}
-
-fn xy(idx: usize, width: usize) -> (usize, usize) {
-    (idx % width, idx / width)
-}
-
-pub fn generate(input: &str) -> (Vec<usize>, usize, usize) {
-    let width = input.lines().next().unwrap().len() + 1; // +1 because of \n
-    let height = input.lines().count();
-    let s_idx = input.find('S').unwrap();
-    let input = input.as_bytes();
-    let mut ret = vec![s_idx];
-    let mut pos = s_idx;
-    let (x, y) = xy(s_idx, width);
-    // check left
-    if x > 0 && [b'-', b'F', b'L'].contains(&input[s_idx - 1]) {
-        pos = s_idx - 1;
-    // check top
-    } else if y > 0 && [b'|', b'7', b'F'].contains(&input[s_idx - width]) {
-        pos = s_idx - width;
-    // check right
-    } else if x < width - 1 && [b'-', b'J', b'7'].contains(&input[s_idx + 1]) {
-        pos = s_idx + 1;
-    // check bottom
-    } else if s_idx + width < input.len() && [b'|', b'L', b'J'].contains(&input[s_idx + width]) {
-        pos = s_idx + width
-    }
-
-    while input[pos] != b'S' {
-        let last_pos = ret[ret.len() - 1];
-        ret.push(pos);
-        match input[pos] {
-            b'|' => {
-                if last_pos != pos - width {
-                    pos -= width;
-                } else {
-                    pos += width;
-                }
-            }
-            b'-' => {
-                if last_pos != pos - 1 {
-                    pos -= 1;
-                } else {
-                    pos += 1;
-                }
-            }
-            b'L' => {
-                if last_pos != pos - width {
-                    pos -= width;
-                } else {
-                    pos += 1;
-                }
+use std::{fmt::Display, str::FromStr};
+
+use crate::utils::Grid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Direction {
+    const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+
+    fn opposite(self) -> Self {
+        match self {
+            Direction::North => Direction::South,
+            Direction::East => Direction::West,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+        }
+    }
+
+    fn offset(self) -> (isize, isize) {
+        match self {
+            Direction::North => (0, -1),
+            Direction::East => (1, 0),
+            Direction::South => (0, 1),
+            Direction::West => (-1, 0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pipe {
+    /// |
+    Vertical,
+    /// -
+    Horizontal,
+    /// L
+    NorthEast,
+    /// J
+    NorthWest,
+    /// 7
+    SouthWest,
+    /// F
+    SouthEast,
+    /// .
+    Ground,
+    /// S, only until its shape is known
+    Start,
+}
+
+impl Pipe {
+    const PIPES: [Pipe; 6] = [
+        Pipe::Vertical,
+        Pipe::Horizontal,
+        Pipe::NorthEast,
+        Pipe::NorthWest,
+        Pipe::SouthWest,
+        Pipe::SouthEast,
+    ];
+
+    pub fn connections(self) -> Option<[Direction; 2]> {
+        use Direction as D;
+        match self {
+            Pipe::Vertical => Some([D::North, D::South]),
+            Pipe::Horizontal => Some([D::East, D::West]),
+            Pipe::NorthEast => Some([D::North, D::East]),
+            Pipe::NorthWest => Some([D::North, D::West]),
+            Pipe::SouthWest => Some([D::South, D::West]),
+            Pipe::SouthEast => Some([D::East, D::South]),
+            Pipe::Ground | Pipe::Start => None,
+        }
+    }
+
+    pub fn connects(self, direction: Direction) -> bool {
+        self.connections().is_some_and(|c| c.contains(&direction))
+    }
+}
+
+impl Display for Pipe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let c = match self {
+            Pipe::Vertical => '│',
+            Pipe::Horizontal => '─',
+            Pipe::NorthEast => '└',
+            Pipe::NorthWest => '┘',
+            Pipe::SouthWest => '┐',
+            Pipe::SouthEast => '┌',
+            Pipe::Ground => '.',
+            Pipe::Start => 'S',
+        };
+        write!(f, "{c}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tile {
+    Loop(Pipe),
+    Inside,
+    Outside,
+}
+
+impl Display for Tile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Tile::Loop(pipe) => write!(f, "{pipe}"),
+            Tile::Inside => write!(f, "I"),
+            Tile::Outside => write!(f, "O"),
+        }
+    }
+}
+
+impl TryFrom<u8> for Pipe {
+    type Error = MazeError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            b'|' => Ok(Pipe::Vertical),
+            b'-' => Ok(Pipe::Horizontal),
+            b'L' => Ok(Pipe::NorthEast),
+            b'J' => Ok(Pipe::NorthWest),
+            b'7' => Ok(Pipe::SouthWest),
+            b'F' => Ok(Pipe::SouthEast),
+            // the puzzle's examples mark enclosed and outside tiles with I and O
+            b'.' | b'I' | b'O' => Ok(Pipe::Ground),
+            b'S' => Ok(Pipe::Start),
+            _ => Err(MazeError::InvalidTile(value as char)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MazeError {
+    InvalidTile(char),
+    MissingStart,
+    MultipleStarts,
+    /// `S` doesn't have exactly two neighbours connecting to it
+    AmbiguousStart,
+    /// the loop leaves the grid or runs into a tile not connecting back
+    OpenLoop,
+}
+
+impl Display for MazeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MazeError::InvalidTile(c) => write!(f, "invalid tile '{c}'"),
+            MazeError::MissingStart => write!(f, "no start tile"),
+            MazeError::MultipleStarts => write!(f, "more than one start tile"),
+            MazeError::AmbiguousStart => {
+                write!(f, "start tile doesn't connect to exactly two pipes")
             }
-            b'J' => {
-                if last_pos != pos - width {
-                    pos -= width;
-                } else {
-                    pos -= 1;
-                }
+            MazeError::OpenLoop => write!(f, "the loop through the start tile isn't closed"),
+        }
+    }
+}
+
+type Coord = (usize, usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    Clockwise,
+    CounterClockwise,
+}
+
+#[derive(Debug, Clone)]
+pub struct PipeMaze {
+    /// every tile, with `S` replaced by its actual shape
+    grid: Grid<Pipe>,
+    start: Coord,
+    /// the tiles of the loop in walking order, beginning at `start`
+    path: Vec<Coord>,
+}
+
+impl PipeMaze {
+    pub fn grid(&self) -> &Grid<Pipe> {
+        &self.grid
+    }
+
+    pub fn start(&self) -> Coord {
+        self.start
+    }
+
+    pub fn path(&self) -> &[Coord] {
+        &self.path
+    }
+
+    /// Twice the signed area of the loop polygon through the tile centres.
+    /// Positive when the loop runs clockwise on screen (y grows downwards).
+    pub fn signed_area2(&self) -> i64 {
+        let mut previous = self.path[self.path.len() - 1];
+        let mut area = 0;
+        for &current in &self.path {
+            let (x1, y1) = (previous.0 as i64, previous.1 as i64);
+            let (x2, y2) = (current.0 as i64, current.1 as i64);
+            area += x1 * y2 - x2 * y1;
+            previous = current;
+        }
+        area
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        if self.signed_area2() > 0 {
+            Orientation::Clockwise
+        } else {
+            Orientation::CounterClockwise
+        }
+    }
+
+    /// Tiles enclosed by the loop, using Pick's theorem `A = i + b/2 - 1`
+    /// where every loop tile is a boundary point of the polygon.
+    pub fn enclosed(&self) -> usize {
+        let area2 = self.signed_area2().unsigned_abs() as usize;
+        (area2 + 2 - self.path.len()) / 2
+    }
+
+    /// Marks every tile as part of the loop, inside or outside of it.
+    ///
+    /// Flood fills the outside on a grid of twice the resolution, where the
+    /// cells between two tiles are only blocked if the loop connects them.
+    /// That way the fill can squeeze between pipes running side by side.
+    pub fn classify(&self) -> Grid<Tile> {
+        let (width, height) = (self.grid.width(), self.grid.height());
+        // tile (x, y) sits at (2x + 1, 2y + 1), the outer ring is padding
+        let (wide, high) = (2 * width + 1, 2 * height + 1);
+        let mut blocked = Grid::new(vec![false; wide * high], wide);
+        for &(x, y) in &self.path {
+            let (cx, cy) = (2 * x + 1, 2 * y + 1);
+            blocked[(cx, cy)] = true;
+            for direction in self.grid[(x, y)].connections().unwrap() {
+                let (dx, dy) = direction.offset();
+                blocked[(cx.wrapping_add_signed(dx), cy.wrapping_add_signed(dy))] = true;
             }
-            b'7' => {
-                if last_pos != pos - 1 {
-                    pos -= 1;
-                } else {
-                    pos += width;
+        }
+
+        let mut outside = Grid::new(vec![false; wide * high], wide);
+        outside[(0, 0)] = true;
+        let mut stack: Vec<Coord> = vec![(0, 0)];
+        while let Some((x, y)) = stack.pop() {
+            for direction in Direction::ALL {
+                let (dx, dy) = direction.offset();
+                let (nx, ny) = (x.wrapping_add_signed(dx), y.wrapping_add_signed(dy));
+                if nx < wide && ny < high && !blocked[(nx, ny)] && !outside[(nx, ny)] {
+                    outside[(nx, ny)] = true;
+                    stack.push((nx, ny));
                 }
             }
-            b'F' => {
-                if last_pos != pos + 1 {
-                    pos += 1;
-                } else {
-                    pos += width;
+        }
+
+        let mut tiles = Grid::new(vec![Tile::Inside; width * height], width);
+        for y in 0..height {
+            for x in 0..width {
+                if outside[(2 * x + 1, 2 * y + 1)] {
+                    tiles[(x, y)] = Tile::Outside;
                 }
             }
-            _ => {
-                unreachable!()
-            }
         }
+        for &(x, y) in &self.path {
+            tiles[(x, y)] = Tile::Loop(self.grid[(x, y)]);
+        }
+        tiles
     }
-    (ret, width, height)
-}
 
-pub fn part1(input: &(Vec<usize>, usize, usize)) -> usize {
-    input.0.len() / 2
-}
+    fn step(&self, (x, y): Coord, direction: Direction) -> Option<Coord> {
+        match direction {
+            Direction::North => y.checked_sub(1).map(|y| (x, y)),
+            Direction::East => (x + 1 < self.grid.width()).then_some((x + 1, y)),
+            Direction::South => (y + 1 < self.grid.height()).then_some((x, y + 1)),
+            Direction::West => x.checked_sub(1).map(|x| (x, y)),
+        }
+    }
+
+    /// The shape `S` has to be to connect to its neighbours.
+    fn infer_start(&self) -> Result<Pipe, MazeError> {
+        let connected: Vec<_> = Direction::ALL
+            .into_iter()
+            .filter(|&d| {
+                self.step(self.start, d)
+                    .is_some_and(|n| self.grid[n].connects(d.opposite()))
+            })
+            .collect();
+        Pipe::PIPES
+            .into_iter()
+            .find(|p| p.connections().is_some_and(|c| c[..] == connected[..]))
+            .ok_or(MazeError::AmbiguousStart)
+    }
 
-fn prev_next(idx: usize, loopidx: &[usize]) -> (usize, usize) {
-    (
-        loopidx[(idx + loopidx.len() - 1) % loopidx.len()],
-        loopidx[(idx + 1) % loopidx.len()],
-    )
+    /// Walks the loop from `start`, checking every step connects back.
+    fn walk(&self) -> Result<Vec<Coord>, MazeError> {
+        let mut path = vec![self.start];
+        let mut direction = self.grid[self.start].connections().unwrap()[0];
+        let mut current = self.start;
+        loop {
+            let next = self
+                .step(current, direction)
+                .filter(|&n| self.grid[n].connects(direction.opposite()))
+                .ok_or(MazeError::OpenLoop)?;
+            if next == self.start {
+                return Ok(path);
+            }
+            let [a, b] = self.grid[next].connections().unwrap();
+            direction = if a == direction.opposite() { b } else { a };
+            path.push(next);
+            current = next;
+        }
+    }
 }
 
-pub fn part2(&(ref loopidx, width, height): &(Vec<usize>, usize, usize)) -> usize {
-    let mut c = 0;
-    for y in 0..height {
-        let mut intersections = 0;
-        let mut last_intersection_direction = isize::MAX;
-        for x in 0..width {
-            if let Some(idx) = loopidx.iter().position(|&e| e == idx(x, y, width)) {
-                let (prev, next) = prev_next(idx, loopidx);
-                let (x_prev, y_prev) = xy(prev, width);
-                let (x_next, y_next) = xy(next, width);
-                let y_direction = if y_prev < y_next { 1 } else { -1 };
+impl FromStr for PipeMaze {
+    type Err = MazeError;
 
-                // only count vertical
-                // and only count if direction changes, otherwise we already counted
-                if [x_next, x_prev].contains(&x) && y_direction != last_intersection_direction {
-                    intersections += 1;
-                    last_intersection_direction = y_direction;
-                }
-            } else if intersections % 2 == 1 {
-                c += 1;
-            }
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let width = s.lines().next().unwrap_or_default().len();
+        let data = s
+            .lines()
+            .flat_map(|l| l.bytes().map(Pipe::try_from))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut starts = data.iter().enumerate().filter(|(_, &p)| p == Pipe::Start);
+        let start = starts.next().ok_or(MazeError::MissingStart)?.0;
+        if starts.next().is_some() {
+            return Err(MazeError::MultipleStarts);
         }
+
+        let mut maze = PipeMaze {
+            grid: Grid::new(data, width),
+            start: (start % width, start / width),
+            path: vec![],
+        };
+        maze.grid[maze.start] = maze.infer_start()?;
+        maze.path = maze.walk()?;
+        Ok(maze)
     }
+}
+
+pub fn generate(input: &str) -> PipeMaze {
+    input.parse().unwrap()
+}
 
-    c
+pub fn part1(input: &PipeMaze) -> usize {
+    input.path.len() / 2
+}
+
+pub fn part2(input: &PipeMaze) -> usize {
+    input.enclosed()
 }
 
 #[cfg(test)]
@@ -205,4 +436,152 @@ L7JLJL-JLJLJL--JLJ.L";
 
         assert_eq!(10, part2(&generate(input)));
     }
+
+    #[test]
+    fn test_start_inference() {
+        let input = "..F7.
+.FJ|.
+SJ.L7
+|F--J
+LJ...";
+        let maze = generate(input);
+
+        assert_eq!((0, 2), maze.start());
+        assert_eq!(Pipe::SouthEast, maze.grid()[(0, 2)]);
+        assert_eq!(16, maze.path().len());
+    }
+
+    #[test]
+    fn test_invalid_mazes() {
+        assert_eq!(
+            Some(MazeError::InvalidTile('x')),
+            ".S-7.\n.|x|.\n.L-J.".parse::<PipeMaze>().err()
+        );
+        assert_eq!(
+            Some(MazeError::MissingStart),
+            ".F-7.\n.|.|.\n.L-J.".parse::<PipeMaze>().err()
+        );
+        assert_eq!(
+            Some(MazeError::MultipleStarts),
+            ".S-7.\n.|.|.\n-S-J.".parse::<PipeMaze>().err()
+        );
+        assert_eq!(
+            Some(MazeError::AmbiguousStart),
+            ".S...\n.|...\n.....".parse::<PipeMaze>().err()
+        );
+        assert_eq!(
+            Some(MazeError::OpenLoop),
+            ".S-7.\n.|.|.\n.L-|.".parse::<PipeMaze>().err()
+        );
+    }
+
+    #[test]
+    fn test_orientation() {
+        let maze = generate(".....\n.S-7.\n.|.|.\n.L-J.\n.....");
+        // the walk leaves S towards the east first
+        assert_eq!(Orientation::Clockwise, maze.orientation());
+        assert_eq!(8, maze.signed_area2());
+        assert_eq!(1, part2(&maze));
+
+        // leaving towards the north walks the other way round
+        let maze = generate(".....\n.F-7.\n.|.|.\n.L-S.\n.....");
+        assert_eq!(Orientation::CounterClockwise, maze.orientation());
+        assert_eq!(-8, maze.signed_area2());
+        assert_eq!(1, part2(&maze));
+    }
+
+    /// A comb shaped loop with `teeth` prongs each `depth` tiles tall,
+    /// separated by a column of outside tiles.
+    fn comb(teeth: usize, depth: usize) -> String {
+        let width = teeth * 5 - 1;
+        let mut top = "F--7.".repeat(teeth).replacen('F', "S", 1);
+        let mut middle = "|..|.".repeat(teeth);
+        let mut bottom = String::new();
+        for t in 0..teeth {
+            bottom += if t == 0 { "|.." } else { "J.." };
+            bottom += if t + 1 == teeth { "|" } else { "L-" };
+        }
+        top.truncate(width);
+        middle.truncate(width);
+        let mut rows = vec![top];
+        rows.extend(std::iter::repeat_n(middle, depth));
+        rows.push(bottom);
+        rows.push(format!("L{}J", "-".repeat(width - 2)));
+        rows.join("\n")
+    }
+
+    #[test]
+    fn test_large() {
+        let maze = generate(&comb(3, 2));
+        assert_eq!(3 * (2 * 2 + 2), part2(&maze));
+
+        let maze = generate(&comb(250, 1000));
+        assert_eq!(250 * (2 * 1000 + 2), part2(&maze));
+    }
+
+    #[test]
+    fn test_classify() {
+        let input = "..........
+.S------7.
+.|F----7|.
+.||OOOO||.
+.||OOOO||.
+.|L-7F-J|.
+.|II||II|.
+.L--JL--J.
+..........";
+        let maze = generate(input);
+        let tiles = maze.classify();
+        let expected = "OOOOOOOOOO
+O┌──────┐O
+O│┌────┐│O
+O││OOOO││O
+O││OOOO││O
+O│└─┐┌─┘│O
+O│II││II│O
+O└──┘└──┘O
+OOOOOOOOOO
+";
+        assert_eq!(expected, tiles.to_string());
+
+        let inside = tiles.iter().filter(|&&t| t == Tile::Inside).count();
+        assert_eq!(part2(&maze), inside);
+    }
+
+    #[test]
+    fn test_classify_matches_count() {
+        let inputs = [
+            comb(4, 3),
+            ".F----7F7F7F7F-7....
+.|F--7||||||||FJ....
+.||.FJ||||||||L7....
+FJL7L7LJLJ||LJ.L-7..
+L--J.L7...LJS7F-7L7.
+....F-J..F7FJ|L7L7L7
+....L7.F7||L7|.L7L7|
+.....|FJLJ|FJ|F7|.LJ
+....FJL-7.||.||||...
+....L---J.LJ.LJLJ..."
+                .to_string(),
+            "FF7FSF7F7F7F7F7F---7
+L|LJ||||||||||||F--J
+FL-7LJLJ||||||LJL-77
+F--JF--7||LJLJ7F7FJ-
+L---JF-JLJ.||-FJLJJ7
+|F|F-JF---7F7-L7L|7|
+|FFJF7L7F-JF7|JL---7
+7-L-JL7||F7|L7F-7F7|
+L.L7LFJ|||||FJL7||LJ
+L7JLJL-JLJLJL--JLJ.L"
+                .to_string(),
+        ];
+        for input in inputs {
+            let maze = generate(&input);
+            let tiles = maze.classify();
+            let inside = tiles.iter().filter(|&&t| t == Tile::Inside).count();
+            let on_loop = tiles.iter().filter(|t| matches!(t, Tile::Loop(_))).count();
+            assert_eq!(part2(&maze), inside);
+            assert_eq!(maze.path().len(), on_loop);
+        }
+    }
 }
@@ -1,73 +1,218 @@
-fn idx(x: usize, y: usize, width: usize) -> usize {
-    x + y * width
-}
+use std::{collections::BinaryHeap, fmt::Write};
 
 type Coord = (usize, usize);
 
-pub fn generate(input: &str) -> Vec<[Coord; 2]> {
-    let height = input.lines().filter(|l| !l.is_empty()).count();
-    let input = input.as_bytes();
-    let width = input.iter().position(|&b| b == b'\n').unwrap();
-    let mut v = vec![];
-    let mut x = 0;
-    let mut x2 = 0;
-    let mut y = 0;
-    let mut y2 = 0;
-    let mut empty_columns = vec![];
-
-    for y_in in 0..height {
-        let mut all_empty = true;
-        for x_in in 0..width {
-            // on first loop, add all the empty columns
-            if y_in == 0
-                && (0..height)
-                    .map(|y| input[idx(x_in, y, width + 1)])
-                    .all(|b| b == b'.')
-            {
-                empty_columns.push(x_in);
-            }
-            if empty_columns.contains(&x_in) {
-                x += 1;
-                x2 += 1_000_000 - 1;
+/// How much space an original row or column takes up after expansion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expansion {
+    /// empty rows and columns grow to `n` times their size
+    Factor(usize),
+    /// explicit size for every row and column, empty or not
+    Weights {
+        rows: Vec<usize>,
+        columns: Vec<usize>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Universe {
+    /// galaxy positions before expansion
+    galaxies: Vec<Coord>,
+    empty_rows: Vec<bool>,
+    empty_columns: Vec<bool>,
+}
+
+impl Universe {
+    pub fn galaxies(&self) -> &[Coord] {
+        &self.galaxies
+    }
+    pub fn width(&self) -> usize {
+        self.empty_columns.len()
+    }
+    pub fn height(&self) -> usize {
+        self.empty_rows.len()
+    }
+
+    /// Galaxy positions after expanding by `expansion`, in input order.
+    pub fn expand(&self, expansion: &Expansion) -> Vec<Coord> {
+        let (rows, columns) = match expansion {
+            Expansion::Factor(n) => {
+                let weights = |empty: &[bool]| -> Vec<usize> {
+                    empty.iter().map(|&e| if e { *n } else { 1 }).collect()
+                };
+                (weights(&self.empty_rows), weights(&self.empty_columns))
             }
-            if input[idx(x_in, y_in, width + 1)] == b'#' {
-                v.push([(x, y), (x2, y2)]);
-                all_empty &= false;
+            Expansion::Weights { rows, columns } => {
+                assert_eq!(self.height(), rows.len(), "one weight per row");
+                assert_eq!(self.width(), columns.len(), "one weight per column");
+                (rows.clone(), columns.clone())
             }
+        };
+        let ys = positions(&rows);
+        let xs = positions(&columns);
+        self.galaxies.iter().map(|&(x, y)| (xs[x], ys[y])).collect()
+    }
+}
 
-            x += 1;
-            x2 += 1;
-        }
-        x = 0;
-        x2 = 0;
-        if all_empty {
-            y += 1;
-            y2 += 1_000_000 - 1;
+/// Start of every row/column given their sizes.
+fn positions(weights: &[usize]) -> Vec<usize> {
+    weights
+        .iter()
+        .scan(0, |start, w| {
+            let position = *start;
+            *start += w;
+            Some(position)
+        })
+        .collect()
+}
+
+pub fn generate(input: &str) -> Universe {
+    let lines: Vec<&[u8]> = input
+        .lines()
+        .filter(|l| !l.is_empty())
+        .map(str::as_bytes)
+        .collect();
+    let width = lines.first().map_or(0, |l| l.len());
+    let mut galaxies = vec![];
+    let mut empty_rows = vec![true; lines.len()];
+    let mut empty_columns = vec![true; width];
+
+    for (y, line) in lines.iter().enumerate() {
+        for (x, &b) in line.iter().enumerate() {
+            if b == b'#' {
+                galaxies.push((x, y));
+                empty_rows[y] = false;
+                empty_columns[x] = false;
+            }
         }
-        y += 1;
-        y2 += 1;
     }
 
-    v
+    Universe {
+        galaxies,
+        empty_rows,
+        empty_columns,
+    }
 }
 
-pub fn solve(input: &[[Coord; 2]], part: usize) -> usize {
+/// Sum of the distances between all pairs of points on a line.
+fn axis_distances(mut values: Vec<usize>) -> usize {
+    values.sort_unstable();
+    let mut prefix = 0;
     let mut sum = 0;
-    for a in 0..input.len() {
-        for b in (a + 1)..input.len() {
-            let (x1, y1) = input[a][part - 1];
-            let (x2, y2) = input[b][part - 1];
-            sum += x1.abs_diff(x2) + y1.abs_diff(y2);
-        }
+    for (i, v) in values.into_iter().enumerate() {
+        // every earlier value is at most v
+        sum += v * i - prefix;
+        prefix += v;
     }
     sum
 }
 
-pub fn part1(input: &[[Coord; 2]]) -> usize {
-    solve(input, 1)
+/// Sum of the Manhattan distances between every pair of galaxies.
+pub fn solve(input: &Universe, expansion: &Expansion) -> usize {
+    let galaxies = input.expand(expansion);
+    let xs = galaxies.iter().map(|g| g.0).collect();
+    let ys = galaxies.iter().map(|g| g.1).collect();
+    axis_distances(xs) + axis_distances(ys)
+}
+
+pub fn distance(a: Coord, b: Coord) -> usize {
+    a.0.abs_diff(b.0) + a.1.abs_diff(b.1)
+}
+
+/// Distances between every pair of galaxies, indexed like the galaxy list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DistanceMatrix {
+    size: usize,
+    distances: Vec<usize>,
 }
-pub fn part2(input: &[[Coord; 2]]) -> usize {
-    solve(input, 2)
+
+impl DistanceMatrix {
+    pub fn new(galaxies: &[Coord]) -> Self {
+        let distances = galaxies
+            .iter()
+            .flat_map(|&a| galaxies.iter().map(move |&b| distance(a, b)))
+            .collect();
+        Self {
+            size: galaxies.len(),
+            distances,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.size
+    }
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+    pub fn get(&self, a: usize, b: usize) -> usize {
+        assert!(a < self.size && b < self.size);
+        self.distances[a * self.size + b]
+    }
+    pub fn row(&self, a: usize) -> &[usize] {
+        &self.distances[a * self.size..(a + 1) * self.size]
+    }
+
+    /// The matrix with a header row and column of galaxy indices.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("galaxy");
+        for b in 0..self.size {
+            write!(csv, ",{b}").unwrap();
+        }
+        csv.push('\n');
+        for a in 0..self.size {
+            write!(csv, "{a}").unwrap();
+            for d in self.row(a) {
+                write!(csv, ",{d}").unwrap();
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// `(galaxy, distance)` of the others, ties go to the lowest index.
+    fn others(&self, a: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.row(a)
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(move |&(b, _)| b != a)
+    }
+
+    /// Closest other galaxy and its distance, `None` if `a` is the only one.
+    pub fn nearest(&self, a: usize) -> Option<(usize, usize)> {
+        self.others(a).min_by_key(|&(b, d)| (d, b))
+    }
+
+    pub fn farthest(&self, a: usize) -> Option<(usize, usize)> {
+        self.others(a)
+            .max_by_key(|&(b, d)| (d, std::cmp::Reverse(b)))
+    }
+
+    /// The `k` closest pairs as `(a, b, distance)` with `a < b`, closest
+    /// first and ties broken by index.
+    pub fn closest_pairs(&self, k: usize) -> Vec<(usize, usize, usize)> {
+        // max heap holding the k best pairs seen so far
+        let mut heap = BinaryHeap::with_capacity(k + 1);
+        for a in 0..self.size {
+            for b in a + 1..self.size {
+                heap.push((self.get(a, b), a, b));
+                if heap.len() > k {
+                    heap.pop();
+                }
+            }
+        }
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|(d, a, b)| (a, b, d))
+            .collect()
+    }
+}
+
+pub fn part1(input: &Universe) -> usize {
+    solve(input, &Expansion::Factor(2))
+}
+pub fn part2(input: &Universe) -> usize {
+    solve(input, &Expansion::Factor(1_000_000))
 }
 
 #[cfg(test)]
@@ -90,4 +235,109 @@ mod test {
 
         assert_eq!(374, part1(&generate(input)));
     }
+
+    #[test]
+    fn test_factors() {
+        let input = "...#......
+.......#..
+#.........
+..........
+......#...
+.#........
+.........#
+..........
+.......#..
+#...#.....";
+        let universe = generate(input);
+
+        assert_eq!(1030, solve(&universe, &Expansion::Factor(10)));
+        assert_eq!(8410, solve(&universe, &Expansion::Factor(100)));
+        assert_eq!(82000210, part2(&universe));
+
+        // no expansion at all, compared against every pair directly
+        let galaxies = universe.galaxies();
+        let mut brute = 0;
+        for (i, a) in galaxies.iter().enumerate() {
+            for b in &galaxies[i + 1..] {
+                brute += a.0.abs_diff(b.0) + a.1.abs_diff(b.1);
+            }
+        }
+        assert_eq!(brute, solve(&universe, &Expansion::Factor(1)));
+    }
+
+    #[test]
+    fn test_weights() {
+        let universe = generate("#..\n...\n..#");
+        assert_eq!(vec![(0, 0), (4, 4)], universe.expand(&Expansion::Factor(3)));
+
+        let weights = Expansion::Weights {
+            rows: vec![1, 2, 7],
+            columns: vec![5, 1, 1],
+        };
+        assert_eq!(vec![(0, 0), (6, 3)], universe.expand(&weights));
+        assert_eq!(9, solve(&universe, &weights));
+    }
+
+    #[test]
+    fn test_distance_matrix() {
+        let input = "...#......
+.......#..
+#.........
+..........
+......#...
+.#........
+.........#
+..........
+.......#..
+#...#.....";
+        let galaxies = generate(input).expand(&Expansion::Factor(2));
+        let matrix = DistanceMatrix::new(&galaxies);
+
+        assert_eq!(9, matrix.len());
+        // the distances called out in the puzzle, galaxies numbered from 1 there
+        assert_eq!(9, matrix.get(4, 8));
+        assert_eq!(15, matrix.get(0, 6));
+        assert_eq!(17, matrix.get(2, 5));
+        assert_eq!(5, matrix.get(7, 8));
+        assert_eq!(matrix.get(8, 4), matrix.get(4, 8));
+        assert_eq!(
+            374,
+            (0..9)
+                .flat_map(|a| (a + 1..9).map(move |b| (a, b)))
+                .map(|(a, b)| matrix.get(a, b))
+                .sum::<usize>()
+        );
+
+        let csv = matrix.to_csv();
+        assert_eq!(10, csv.lines().count());
+        assert!(csv.starts_with("galaxy,0,1,2,3,4,5,6,7,8\n0,0,6,6,"));
+
+        // galaxies 6 and 7 are both 5 away, the lower index wins
+        assert_eq!(Some((6, 5)), matrix.nearest(8));
+        assert_eq!(Some((1, 14)), matrix.farthest(8));
+        assert_eq!(None, DistanceMatrix::new(&galaxies[..1]).nearest(0));
+    }
+
+    #[test]
+    fn test_closest_pairs() {
+        let galaxies = [(0, 0), (10, 0), (1, 1), (0, 3), (10, 2)];
+        let matrix = DistanceMatrix::new(&galaxies);
+
+        assert_eq!(
+            vec![(0, 2, 2), (1, 4, 2), (0, 3, 3)],
+            matrix.closest_pairs(3)
+        );
+        assert!(matrix.closest_pairs(0).is_empty());
+        assert_eq!(10, matrix.closest_pairs(100).len());
+
+        let mut all = vec![];
+        for a in 0..5 {
+            for b in a + 1..5 {
+                all.push((matrix.get(a, b), a, b));
+            }
+        }
+        all.sort();
+        let expected: Vec<_> = all.into_iter().map(|(d, a, b)| (a, b, d)).collect();
+        assert_eq!(expected, matrix.closest_pairs(10));
+    }
 }
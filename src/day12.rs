@@ -1,4 +1,6 @@
-use itertools::{intersperse, repeat_n, EitherOrBoth, Itertools};
+use std::collections::HashMap;
+
+use itertools::{intersperse, repeat_n};
 use rayon::prelude::*;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -32,68 +34,75 @@ pub fn generate(input: &str) -> Vec<SpringRow> {
     input.lines().map(parse_line).collect()
 }
 
-fn check_configuration(configuration: &[Spring], row: &[Spring], check: &[usize]) -> bool {
-    let mut seen = 0;
-    let mut c = vec![];
-    for &r in row {
-        if r == Spring::Unknown {
-            c.push(configuration[seen]);
-            seen += 1;
-        } else {
-            c.push(r);
+/// The row repeated `factor` times, joined by unknown springs.
+pub fn unfold((springs, groups): &SpringRow, factor: usize) -> SpringRow {
+    (
+        intersperse(repeat_n(springs.iter(), factor), [Spring::Unknown].iter())
+            .flatten()
+            .copied()
+            .collect(),
+        repeat_n(groups.iter(), factor).flatten().copied().collect(),
+    )
+}
+
+struct Counter<'a> {
+    springs: &'a [Spring],
+    groups: &'a [usize],
+    memo: HashMap<(usize, usize, usize), usize>,
+}
+
+impl Counter<'_> {
+    /// Arrangements of `springs[position..]` given `run` damaged springs
+    /// directly before it that count towards `groups[group]`.
+    fn count(&mut self, position: usize, group: usize, run: usize) -> usize {
+        if position == self.springs.len() {
+            let done = group == self.groups.len() && run == 0
+                || group + 1 == self.groups.len() && run == self.groups[group];
+            return done as usize;
         }
-    }
-    let groups = c.iter().group_by(|&c| c);
-    let broken_count_ranges = groups.into_iter().filter_map(|(&k, group)| {
-        if k == Spring::Damaged {
-            Some(group.count())
-        } else {
-            None
+        if let Some(&c) = self.memo.get(&(position, group, run)) {
+            return c;
         }
-    });
 
-    for z in check.iter().zip_longest(broken_count_ranges) {
-        if let EitherOrBoth::Both(&c, count) = z {
-            if c != count {
-                return false;
+        let spring = self.springs[position];
+        let mut c = 0;
+        if spring != Spring::Ok && group < self.groups.len() && run < self.groups[group] {
+            c += self.count(position + 1, group, run + 1);
+        }
+        if spring != Spring::Damaged {
+            if run == 0 {
+                c += self.count(position + 1, group, 0);
+            } else if run == self.groups[group] {
+                c += self.count(position + 1, group + 1, 0);
             }
-        } else {
-            return false;
         }
+
+        self.memo.insert((position, group, run), c);
+        c
     }
+}
 
-    true
+pub fn count_arrangements((springs, groups): &SpringRow) -> usize {
+    Counter {
+        springs,
+        groups,
+        memo: HashMap::new(),
+    }
+    .count(0, 0, 0)
 }
 
-fn count_options((springoptions, springcount): &SpringRow) -> usize {
-    // optimize to only generate partitions of unkown
-    let unknown_count = springoptions
-        .iter()
-        .filter(|&&so| so == Spring::Unknown)
-        .count();
-    repeat_n([Spring::Ok, Spring::Damaged], unknown_count)
-        .multi_cartesian_product()
-        .filter(|p| check_configuration(p, springoptions, springcount))
-        .count()
+pub fn solve(input: &[SpringRow], factor: usize) -> usize {
+    input
+        .par_iter()
+        .map(|row| count_arrangements(&unfold(row, factor)))
+        .sum()
 }
 
 pub fn part1(input: &[SpringRow]) -> usize {
-    input.par_iter().map(count_options).sum()
+    solve(input, 1)
 }
 pub fn part2(input: &[SpringRow]) -> usize {
-    let input: Vec<_> = input
-        .iter()
-        .map(|(springoptions, springcount)| {
-            (
-                intersperse(repeat_n(springoptions.iter(), 5), [Spring::Unknown].iter())
-                    .flatten()
-                    .cloned()
-                    .collect(),
-                repeat_n(springcount.iter(), 5).flatten().cloned().collect(),
-            )
-        })
-        .collect();
-    part1(&input)
+    solve(input, 5)
 }
 
 #[cfg(test)]
@@ -117,29 +126,41 @@ mod test {
 
         assert_eq!(1, part1(&generate(input)));
     }
-    //     #[test]
-    //     fn test_p2_single() {
-    //         let input = "???.### 1,1,3";
-
-    //         // this should only be about 5 times + 4 slower than test_p1_single
-    //         assert_eq!(1, part2(&generate(input)));
-    //     }
-    //     #[test]
-    //     fn test_p2_single_complex() {
-    //         let input = ".??..??...?##. 1,1,3";
-
-    //         // this will still be slow af
-    //         assert_eq!(16384, part2(&generate(input)));
-    //     }
-    //     #[test]
-    //     fn test_p2() {
-    //         let input = "???.### 1,1,3
-    // .??..??...?##. 1,1,3
-    // ?#?#?#?#?#?#?#? 1,3,1,6
-    // ????.#...#... 4,1,1
-    // ????.######..#####. 1,6,5
-    // ?###???????? 3,2,1";
-
-    //         assert_eq!(525152, part2(&generate(input)));
-    //     }
+    #[test]
+    fn test_p2_single() {
+        let input = "???.### 1,1,3";
+
+        assert_eq!(1, part2(&generate(input)));
+    }
+    #[test]
+    fn test_p2_single_complex() {
+        let input = ".??..??...?##. 1,1,3";
+
+        assert_eq!(16384, part2(&generate(input)));
+    }
+    #[test]
+    fn test_p2() {
+        let input = "???.### 1,1,3
+.??..??...?##. 1,1,3
+?#?#?#?#?#?#?#? 1,3,1,6
+????.#...#... 4,1,1
+????.######..#####. 1,6,5
+?###???????? 3,2,1";
+
+        assert_eq!(525152, part2(&generate(input)));
+    }
+
+    #[test]
+    fn test_factors() {
+        let input = generate("?###???????? 3,2,1");
+
+        assert_eq!(10, solve(&input, 1));
+        assert_eq!(506250, solve(&input, 5));
+        // every copy but the first has an extra leading '?' to place the 3 in
+        assert_eq!(10 * 15usize.pow(9), solve(&input, 10));
+
+        let long = generate(&format!("{} 1,1", "?".repeat(40)));
+        // choose 2 non adjacent positions out of 40
+        assert_eq!(39 * 38 / 2, part1(&long));
+    }
 }
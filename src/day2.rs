@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 #[derive(Debug, Clone, Default)]
 pub struct Draw {
     red: usize,
@@ -5,12 +7,24 @@ pub struct Draw {
     green: usize,
 }
 
-type Game = Vec<Draw>;
+#[derive(Debug, Clone)]
+pub struct Game {
+    id: usize,
+    draws: Vec<Draw>,
+}
 
 pub fn generate(input: &str) -> Vec<Game> {
     let mut games = vec![];
+    let mut ids = HashSet::new();
     for l in input.lines() {
-        let game = l.split_once(':').expect("line to contain :").1;
+        let (id, game) = l.split_once(':').expect("line to contain :");
+        let id: usize = id
+            .strip_prefix("Game ")
+            .expect("line to start with Game")
+            .trim()
+            .parse()
+            .expect("to parse a game id");
+        assert!(ids.insert(id), "Duplicate game id {id}");
         let mut draws = vec![];
         for draw in game.split(';') {
             let mut d = Draw::default();
@@ -28,7 +42,7 @@ pub fn generate(input: &str) -> Vec<Game> {
             }
             draws.push(d);
         }
-        games.push(draws)
+        games.push(Game { id, draws })
     }
     games
 }
@@ -36,12 +50,12 @@ pub fn generate(input: &str) -> Vec<Game> {
 pub fn part1(input: &[Game]) -> usize {
     input
         .iter()
-        .enumerate()
-        .filter(|(_, g)| {
-            g.iter()
+        .filter(|g| {
+            g.draws
+                .iter()
                 .all(|d| d.red <= 12 && d.blue <= 14 && d.green <= 13)
         })
-        .map(|(i, _)| i + 1)
+        .map(|g| g.id)
         .sum()
 }
 
@@ -49,7 +63,7 @@ pub fn part2(input: &[Game]) -> usize {
     input
         .iter()
         .map(|g| {
-            g.iter().fold(Draw::default(), |a, c| Draw {
+            g.draws.iter().fold(Draw::default(), |a, c| Draw {
                 red: a.red.max(c.red),
                 blue: a.blue.max(c.blue),
                 green: a.green.max(c.green),
@@ -84,4 +98,22 @@ Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green
 ";
         assert_eq!(2286, part2(&generate(input)));
     }
+
+    #[test]
+    fn test_p1_real_ids() {
+        let input = "Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green
+Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
+Game 17: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
+";
+        assert_eq!(22, part1(&generate(input)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate game id")]
+    fn test_duplicate_ids() {
+        let input = "Game 1: 3 blue, 4 red
+Game 1: 1 blue, 2 green
+";
+        generate(input);
+    }
 }
@@ -1,103 +1,272 @@
-use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 
-fn surrounding(start: usize, size: usize, width: usize, height: usize) -> Vec<usize> {
-    // +1 because of \n
-    let x_start = start % width;
-    let y = start / width;
-    let span = x_start.saturating_sub(1)..=(x_start + size).clamp(0, width);
-    let mut idxs = vec![];
-    if y > 0 {
-        idxs.extend(span.clone().map(|x| (y - 1) * width + x));
+use crate::utils::Grid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cell {
+    Empty,
+    Digit(u8),
+    Symbol(u8),
+}
+
+impl From<u8> for Cell {
+    fn from(value: u8) -> Self {
+        match value {
+            b'.' => Cell::Empty,
+            b'0'..=b'9' => Cell::Digit(value - b'0'),
+            _ => Cell::Symbol(value),
+        }
     }
-    if y < height - 1 {
-        idxs.extend(span.map(|x| (y + 1) * width + x));
+}
+
+#[derive(Debug, Clone)]
+pub struct Number {
+    value: usize,
+    y: usize,
+    span: Range<usize>,
+}
+
+impl Number {
+    pub fn value(&self) -> usize {
+        self.value
     }
-    if x_start > 0 {
-        idxs.push(y * width + x_start - 1);
+    pub fn y(&self) -> usize {
+        self.y
     }
-    if x_start + size < width - 1 {
-        idxs.push(y * width + x_start + size);
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
     }
-    idxs
 }
 
-pub fn part1(input: &str) -> usize {
-    let mut numbers: Vec<usize> = vec![];
-    let width = input.lines().next().unwrap().len();
-    let height = input.lines().count();
-    let mut index = 0;
-    while index < input.len() {
-        if !input.chars().nth(index).unwrap().is_ascii_digit() {
-            index += 1;
-            continue;
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    symbol: u8,
+    x: usize,
+    y: usize,
+}
+
+impl Symbol {
+    pub fn symbol(&self) -> u8 {
+        self.symbol
+    }
+    pub fn position(&self) -> (usize, usize) {
+        (self.x, self.y)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Adjacency {
+    /// up, down, left and right
+    Four,
+    /// including diagonals
+    Eight,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NeighbourCount {
+    Exact(usize),
+    Min(usize),
+    Max(usize),
+}
+
+impl NeighbourCount {
+    fn matches(self, count: usize) -> bool {
+        match self {
+            NeighbourCount::Exact(n) => count == n,
+            NeighbourCount::Min(n) => count >= n,
+            NeighbourCount::Max(n) => count <= n,
         }
-        let num: String = input
-            .chars()
-            .skip(index)
-            .take_while(|c| c.is_ascii_digit())
-            .collect();
-        let idxs = surrounding(index, num.len(), width + 1, height);
-        if idxs.iter().any(|i| {
-            let c = input.chars().nth(*i).unwrap();
-            !c.is_ascii_digit() && c != '.' && c != '\n'
-        }) {
-            numbers.push(num.parse().unwrap());
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregation {
+    Product,
+    Sum,
+    Max,
+}
+
+impl Aggregation {
+    fn apply(self, values: impl Iterator<Item = usize>) -> usize {
+        match self {
+            Aggregation::Product => values.product(),
+            Aggregation::Sum => values.sum(),
+            Aggregation::Max => values.max().unwrap_or(0),
         }
-        index += num.len();
     }
+}
 
-    numbers.into_iter().sum()
+/// Which symbols to look at and how to combine the numbers next to them.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    /// symbols the rule applies to, empty means every symbol
+    pub symbols: Vec<u8>,
+    /// how many distinct numbers have to touch the symbol
+    pub count: NeighbourCount,
+    pub adjacency: Adjacency,
+    pub aggregation: Aggregation,
 }
 
-pub fn part2(input: &str) -> usize {
-    let mut numbers = HashMap::new();
-    let width = input.lines().next().unwrap().len();
-    let height = input.lines().count();
-    let mut index = 0;
-
-    while index < input.len() {
-        if input.chars().nth(index).unwrap().is_ascii_digit() {
-            let num: String = input
-                .chars()
-                .skip(index)
-                .take_while(|c| c.is_ascii_digit())
-                .collect();
-            numbers.insert(index..(index + num.len()), num.parse::<usize>().unwrap());
-            index += num.len();
-        } else {
-            index += 1;
+impl Rule {
+    /// a '*' touching exactly two numbers, multiplied together
+    pub fn gear() -> Self {
+        Self {
+            symbols: vec![b'*'],
+            count: NeighbourCount::Exact(2),
+            adjacency: Adjacency::Eight,
+            aggregation: Aggregation::Product,
         }
     }
+}
 
-    let positions: Vec<_> = input
-        .char_indices()
-        .filter_map(|(i, c)| {
-            if c == '*' {
-                Some(surrounding(i, 1, width + 1, height))
-            } else {
-                None
-            }
-        })
-        .collect();
-    let mut res = 0;
-    for s in positions {
-        // this would totally break if a '*' was adjacent to the same number twice, eg:
-        // 123*123
-        // thankfully this doesnt happen in the input
-        let s: HashSet<_> = s
+#[derive(Debug, Clone)]
+pub struct RuleMatch<'a> {
+    pub symbol: &'a Symbol,
+    /// the distinct numbers touching the symbol
+    pub numbers: Vec<&'a Number>,
+    pub value: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Schematic {
+    cells: Grid<Cell>,
+    numbers: Vec<Number>,
+    symbols: Vec<Symbol>,
+    /// index into `numbers` for every cell that is part of a number
+    number_ids: Grid<Option<usize>>,
+}
+
+impl Schematic {
+    pub fn cells(&self) -> &Grid<Cell> {
+        &self.cells
+    }
+    pub fn numbers(&self) -> &[Number] {
+        &self.numbers
+    }
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+    pub fn number_at(&self, x: usize, y: usize) -> Option<&Number> {
+        self.number_ids[(x, y)].map(|id| &self.numbers[id])
+    }
+
+    fn neighbours(
+        &self,
+        x: usize,
+        y: usize,
+        adjacency: Adjacency,
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let xs = x.saturating_sub(1)..(x + 2).min(self.cells.width());
+        let ys = y.saturating_sub(1)..(y + 2).min(self.cells.height());
+        ys.flat_map(move |ny| xs.clone().map(move |nx| (nx, ny)))
+            .filter(move |&(nx, ny)| match adjacency {
+                Adjacency::Four => (nx == x) != (ny == y),
+                Adjacency::Eight => (nx, ny) != (x, y),
+            })
+    }
+
+    /// ids of the numbers touching the given cell, once per touching cell
+    fn adjacent_numbers(
+        &self,
+        x: usize,
+        y: usize,
+        adjacency: Adjacency,
+    ) -> impl Iterator<Item = usize> + '_ {
+        self.neighbours(x, y, adjacency)
+            .filter_map(|n| self.number_ids[n])
+    }
+
+    /// Evaluates `rule` for every matching symbol whose neighbour count fits.
+    pub fn apply(&self, rule: &Rule) -> Vec<RuleMatch<'_>> {
+        self.symbols
             .iter()
-            .filter_map(|idx| {
-                numbers
-                    .iter()
-                    .find(|(k, _)| k.contains(idx))
-                    .map(|(_, v)| *v)
+            .filter(|s| rule.symbols.is_empty() || rule.symbols.contains(&s.symbol))
+            .filter_map(|s| {
+                // dedup by number id, a number can touch the same symbol with several digits
+                // and two distinct numbers can share the same value, eg: 123*123
+                let mut ids: Vec<_> = self.adjacent_numbers(s.x, s.y, rule.adjacency).collect();
+                ids.sort_unstable();
+                ids.dedup();
+                if !rule.count.matches(ids.len()) {
+                    return None;
+                }
+                let numbers: Vec<_> = ids.into_iter().map(|id| &self.numbers[id]).collect();
+                let value = rule.aggregation.apply(numbers.iter().map(|n| n.value));
+                Some(RuleMatch {
+                    symbol: s,
+                    numbers,
+                    value,
+                })
             })
-            .collect();
-        if s.len() == 2 {
-            res += s.into_iter().product::<usize>();
+            .collect()
+    }
+}
+
+pub fn generate(input: &str) -> Schematic {
+    let width = input.lines().next().unwrap().len();
+    let data: Vec<Cell> = input
+        .lines()
+        .flat_map(|l| l.bytes().map(Cell::from))
+        .collect();
+    let cells = Grid::new(data, width);
+    let mut number_ids = Grid::new(vec![None; width * cells.height()], width);
+    let mut numbers = vec![];
+    let mut symbols = vec![];
+
+    for y in 0..cells.height() {
+        let mut x = 0;
+        while x < width {
+            match cells[(x, y)] {
+                Cell::Empty => x += 1,
+                Cell::Symbol(symbol) => {
+                    symbols.push(Symbol { symbol, x, y });
+                    x += 1;
+                }
+                Cell::Digit(_) => {
+                    let start = x;
+                    let mut value = 0;
+                    while let Some(&Cell::Digit(d)) = (x < width).then(|| &cells[(x, y)]) {
+                        value = value * 10 + d as usize;
+                        number_ids[(x, y)] = Some(numbers.len());
+                        x += 1;
+                    }
+                    numbers.push(Number {
+                        value,
+                        y,
+                        span: start..x,
+                    });
+                }
+            }
+        }
+    }
+
+    Schematic {
+        cells,
+        numbers,
+        symbols,
+        number_ids,
+    }
+}
+
+pub fn part1(input: &Schematic) -> usize {
+    let mut adjacent = vec![false; input.numbers.len()];
+    for s in &input.symbols {
+        for id in input.adjacent_numbers(s.x, s.y, Adjacency::Eight) {
+            adjacent[id] = true;
         }
     }
-    res
+
+    input
+        .numbers
+        .iter()
+        .zip(adjacent)
+        .filter(|(_, a)| *a)
+        .map(|(n, _)| n.value)
+        .sum()
+}
+
+pub fn part2(input: &Schematic) -> usize {
+    input.apply(&Rule::gear()).iter().map(|m| m.value).sum()
 }
 
 #[cfg(test)]
@@ -117,7 +286,7 @@ mod test {
 ...$.*....
 .664.598..";
 
-        assert_eq!(4361, part1(input));
+        assert_eq!(4361, part1(&generate(input)));
     }
     #[test]
     fn test_p2() {
@@ -132,6 +301,65 @@ mod test {
 ...$.*....
 .664.598..";
 
-        assert_eq!(467835, part2(input));
+        assert_eq!(467835, part2(&generate(input)));
+    }
+
+    #[test]
+    fn test_large() {
+        let row = "12*34.....".repeat(100);
+        let empty = ".".repeat(row.len());
+        let input = [row.as_str(), empty.as_str()].repeat(500).join("\n");
+        let schematic = generate(&input);
+
+        assert_eq!(46 * 100 * 500, part1(&schematic));
+        assert_eq!(408 * 100 * 500, part2(&schematic));
+    }
+
+    #[test]
+    fn test_p2_equal_values() {
+        let input = "123*123
+.......";
+
+        assert_eq!(123 * 123, part2(&generate(input)));
+    }
+
+    #[test]
+    fn test_p2_touching_twice() {
+        let input = "123.
+.*..
+..45";
+
+        assert_eq!(123 * 45, part2(&generate(input)));
+    }
+
+    #[test]
+    fn test_rules() {
+        let input = "2.3..
+.#+7.
+4.5..";
+        let schematic = generate(input);
+
+        let diagonal = Rule {
+            symbols: vec![b'#'],
+            count: NeighbourCount::Min(1),
+            adjacency: Adjacency::Eight,
+            aggregation: Aggregation::Sum,
+        };
+        let matches = schematic.apply(&diagonal);
+        assert_eq!(1, matches.len());
+        assert_eq!(14, matches[0].value);
+
+        let straight = Rule {
+            symbols: vec![],
+            count: NeighbourCount::Max(3),
+            adjacency: Adjacency::Four,
+            aggregation: Aggregation::Max,
+        };
+        let values: Vec<_> = schematic
+            .apply(&straight)
+            .iter()
+            .map(|m| (m.symbol.symbol(), m.value))
+            .collect();
+        assert_eq!(vec![(b'#', 0), (b'+', 7)], values);
     }
 }
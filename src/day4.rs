@@ -1,50 +1,105 @@
-use std::collections::HashSet;
+use std::collections::HashMap;
 
-type Deck = Vec<usize>;
+#[derive(Debug, Clone)]
+pub struct Card {
+    id: usize,
+    winning: Vec<usize>,
+    numbers: Vec<usize>,
+}
+
+impl Card {
+    pub fn id(&self) -> usize {
+        self.id
+    }
+    pub fn winning(&self) -> &[usize] {
+        &self.winning
+    }
+    pub fn numbers(&self) -> &[usize] {
+        &self.numbers
+    }
+
+    /// the numbers we have that are also winning numbers
+    pub fn matches(&self) -> impl Iterator<Item = usize> + '_ {
+        self.numbers
+            .iter()
+            .copied()
+            .filter(|n| self.winning.contains(n))
+    }
+
+    pub fn score(&self, scoring: &Scoring) -> usize {
+        match scoring {
+            Scoring::Doubling => match self.matches().count() {
+                0 => 0,
+                matching => 1 << (matching - 1),
+            },
+            Scoring::Linear => self.matches().count(),
+            Scoring::Weighted(weights) => self
+                .matches()
+                .map(|n| weights.get(&n).copied().unwrap_or(0))
+                .sum(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Scoring {
+    /// 1 point for the first match, doubled for every further match
+    Doubling,
+    /// 1 point per match
+    Linear,
+    /// every matching number scores its weight, numbers without a weight score nothing
+    Weighted(HashMap<usize, usize>),
+}
+
+type Deck = Vec<Card>;
 
 pub fn generate(input: &str) -> Deck {
     let mut v = vec![];
     for l in input.lines() {
-        let (_, numbers) = l.split_once(':').unwrap();
-        let (my, winning) = numbers.trim().split_once(" | ").unwrap();
-        let my: HashSet<usize> = my.split_whitespace().flat_map(|n| n.parse().ok()).collect();
+        let (id, numbers) = l.split_once(':').unwrap();
+        let id = id.strip_prefix("Card").unwrap().trim().parse().unwrap();
+        let (winning, numbers) = numbers.trim().split_once(" | ").unwrap();
         let winning = winning
             .split_whitespace()
             .flat_map(|n| n.parse().ok())
             .collect();
-        v.push(my.intersection(&winning).count());
+        let numbers = numbers
+            .split_whitespace()
+            .flat_map(|n| n.parse().ok())
+            .collect();
+        v.push(Card {
+            id,
+            winning,
+            numbers,
+        });
     }
     v
 }
 
-pub fn part1(input: &Deck) -> usize {
-    input
-        .iter()
-        .map(
-            |&matching| {
-                if matching > 0 {
-                    1 << (matching - 1)
-                } else {
-                    0
-                }
-            },
-        )
-        .sum()
+pub fn total_score(input: &Deck, scoring: &Scoring) -> usize {
+    input.iter().map(|c| c.score(scoring)).sum()
 }
 
-fn process(input: &Deck, current: usize) -> usize {
-    let matching = input[current];
+pub fn part1(input: &Deck) -> usize {
+    total_score(input, &Scoring::Doubling)
+}
 
-    if 0 == matching {
-        1
-    } else {
-        1 + (current..(current + matching))
-            .map(|i| process(input, i + 1))
-            .sum::<usize>()
+/// Number of copies of every card after all wins have been processed.
+/// Cards only ever win copies of later cards, so a single forward pass is enough.
+pub fn copies(input: &Deck) -> Vec<usize> {
+    let mut copies = vec![1; input.len()];
+    for (i, card) in input.iter().enumerate() {
+        let matching = card.matches().count();
+        let end = (i + 1 + matching).min(input.len());
+        for j in i + 1..end {
+            copies[j] += copies[i];
+        }
     }
+    copies
 }
+
 pub fn part2(input: &Deck) -> usize {
-    (0..input.len()).map(|i| process(input, i)).sum()
+    copies(input).into_iter().sum()
 }
 
 #[cfg(test)]
@@ -73,4 +128,43 @@ Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
 
         assert_eq!(30, part2(&generate(input)))
     }
+
+    #[test]
+    fn test_copies() {
+        let input = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
+Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
+Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
+Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
+
+        assert_eq!(vec![1, 2, 4, 8, 14, 1], copies(&generate(input)))
+    }
+    #[test]
+    fn test_scoring() {
+        let input = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
+Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
+Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
+Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
+        let deck = generate(input);
+
+        assert_eq!(13, total_score(&deck, &Scoring::Doubling));
+        assert_eq!(4 + 2 + 2 + 1, total_score(&deck, &Scoring::Linear));
+        let weights = HashMap::from([(48, 10), (84, 5), (1, 1)]);
+        assert_eq!(16, total_score(&deck, &Scoring::Weighted(weights)));
+    }
+    #[test]
+    fn test_p2_long_chain() {
+        let deck: Deck = (1..=5000)
+            .map(|id| Card {
+                id,
+                winning: vec![id],
+                numbers: vec![id],
+            })
+            .collect();
+
+        assert_eq!(5000 * 5001 / 2, part2(&deck))
+    }
 }
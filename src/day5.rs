@@ -1,212 +1,457 @@
-use rayon::prelude::*;
-use std::ops::Range;
+use itertools::Itertools;
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Display,
+    ops::Range,
+    str::FromStr,
+};
+
+use crate::utils::IntervalSet;
 
 type Map = (usize, usize, usize);
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AlmanacError {
+    InvalidSeeds(String),
+    InvalidHeader(String),
+    InvalidEntry(String),
+    DuplicateMap {
+        from: String,
+        to: String,
+    },
+    /// two entries of the same map share source values
+    OverlappingEntries {
+        from: String,
+        to: String,
+    },
+    /// the category is part of a cycle of maps
+    Cycle(String),
+    UnknownCategory(String),
+    Unreachable {
+        from: String,
+        to: String,
+    },
+}
+
+impl Display for AlmanacError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AlmanacError::InvalidSeeds(l) => write!(f, "invalid seeds line: {l:?}"),
+            AlmanacError::InvalidHeader(l) => write!(f, "invalid map header: {l:?}"),
+            AlmanacError::InvalidEntry(l) => write!(f, "invalid map entry: {l:?}"),
+            AlmanacError::DuplicateMap { from, to } => {
+                write!(f, "duplicate map from {from} to {to}")
+            }
+            AlmanacError::OverlappingEntries { from, to } => {
+                write!(f, "overlapping source ranges in map from {from} to {to}")
+            }
+            AlmanacError::Cycle(c) => write!(f, "category {c} is part of a cycle"),
+            AlmanacError::UnknownCategory(c) => write!(f, "unknown category {c}"),
+            AlmanacError::Unreachable { from, to } => {
+                write!(f, "{to} is not reachable from {from}")
+            }
+        }
+    }
+}
+
+/// A single stage, stored as a sorted table of pieces covering every `usize`
+/// below `usize::MAX`. Gaps between map entries are explicit identity pieces.
 #[derive(Debug, Clone)]
 struct Mapping {
-    map: Vec<Map>,
-    next: Option<Box<Mapping>>,
+    from: String,
+    to: String,
+    table: Vec<Piece>,
 }
 
 impl Mapping {
-    fn match_item(&self, input: usize) -> (usize, Option<&Mapping>) {
-        let dest = self
-            .map
+    fn new(from: &str, to: &str, mut map: Vec<Map>) -> Result<Self, AlmanacError> {
+        map.retain(|&(_, _, count)| count > 0);
+        map.sort_unstable_by_key(|&(_, source, _)| source);
+        let mut table = vec![];
+        let mut pos = 0;
+        for (dest, source, count) in map {
+            if source < pos {
+                return Err(AlmanacError::OverlappingEntries {
+                    from: from.to_string(),
+                    to: to.to_string(),
+                });
+            }
+            if pos < source {
+                table.push(Piece {
+                    source: pos..source,
+                    offset: 0,
+                });
+            }
+            table.push(Piece {
+                source: source..source + count,
+                offset: dest as isize - source as isize,
+            });
+            pos = source + count;
+        }
+        table.push(Piece {
+            source: pos..usize::MAX,
+            offset: 0,
+        });
+        Ok(Self {
+            from: from.to_string(),
+            to: to.to_string(),
+            table,
+        })
+    }
+
+    fn match_item(&self, input: usize) -> usize {
+        let i = self.table.partition_point(|p| p.source.end <= input);
+        Piece::shift(input, self.table[i].offset)
+    }
+
+    /// Splits `input` wherever it crosses the boundary of a map entry,
+    /// the pieces are sorted by source.
+    fn split(&self, input: Range<usize>) -> Vec<Piece> {
+        let first = self.table.partition_point(|p| p.source.end <= input.start);
+        self.table[first..]
             .iter()
-            .find(|(_, source, count)| input >= *source && input < *source + *count)
-            .map(|(dest, source, _)| dest + (input - source))
-            .unwrap_or(input);
-        (dest, self.next.as_deref())
+            .take_while(|p| p.source.start < input.end)
+            .map(|p| Piece {
+                source: p.source.start.max(input.start)..p.source.end.min(input.end),
+                offset: p.offset,
+            })
+            .collect()
+    }
+
+    /// Maps a whole range at once, unmapped parts are passed through unchanged.
+    fn match_range(&self, input: Range<usize>) -> Vec<Range<usize>> {
+        self.split(input).iter().map(Piece::image).collect()
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct Almanac {
-    seeds: Vec<usize>,
-    maps: Mapping,
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Piece {
+    pub source: Range<usize>,
+    pub offset: isize,
 }
-pub fn part1(input: &Almanac) -> usize {
-    input
-        .seeds
-        .iter()
-        .map(|s| {
-            let mut next = Some(&input.maps);
-            let mut current = *s;
-            loop {
-                let (c, n) = next.unwrap().match_item(current);
-                if n.is_none() {
-                    return c;
+
+impl Piece {
+    fn shift(value: usize, offset: isize) -> usize {
+        value.checked_add_signed(offset).unwrap()
+    }
+
+    pub fn image(&self) -> Range<usize> {
+        Self::shift(self.source.start, self.offset)..Self::shift(self.source.end, self.offset)
+    }
+}
+
+/// A whole chain of mappings collapsed into one piecewise linear function.
+/// The pieces are sorted by source and cover every `usize` below `usize::MAX`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Composed {
+    pieces: Vec<Piece>,
+}
+
+impl Composed {
+    fn identity() -> Self {
+        Self {
+            pieces: vec![Piece {
+                source: 0..usize::MAX,
+                offset: 0,
+            }],
+        }
+    }
+
+    fn then(&self, mapping: &Mapping) -> Self {
+        let mut pieces: Vec<Piece> = vec![];
+        for p in &self.pieces {
+            for s in mapping.split(p.image()) {
+                let source =
+                    Piece::shift(s.source.start, -p.offset)..Piece::shift(s.source.end, -p.offset);
+                let offset = p.offset + s.offset;
+                match pieces.last_mut() {
+                    Some(last) if last.offset == offset && last.source.end == source.start => {
+                        last.source.end = source.end
+                    }
+                    _ => pieces.push(Piece { source, offset }),
                 }
-                next = n;
-                current = c;
             }
-        })
-        .min()
-        .unwrap()
+        }
+        Self { pieces }
+    }
+
+    pub fn pieces(&self) -> &[Piece] {
+        &self.pieces
+    }
+
+    pub fn apply(&self, value: usize) -> usize {
+        let i = self.pieces.partition_point(|p| p.source.end <= value);
+        Piece::shift(value, self.pieces[i].offset)
+    }
+
+    /// Every value reachable from the given input values.
+    pub fn image(&self, ranges: &IntervalSet<usize>) -> IntervalSet<usize> {
+        ranges
+            .iter()
+            .flat_map(|r| {
+                let first = self.pieces.partition_point(|p| p.source.end <= r.start);
+                self.pieces[first..]
+                    .iter()
+                    .take_while(|p| p.source.start < r.end)
+                    .map(|p| {
+                        Piece {
+                            source: p.source.start.max(r.start)..p.source.end.min(r.end),
+                            offset: p.offset,
+                        }
+                        .image()
+                    })
+            })
+            .collect()
+    }
+
+    /// The pieces mapping back from destination to source, sorted by destination.
+    /// These can overlap if several sources land on the same destination.
+    pub fn inverse(&self) -> Vec<Piece> {
+        let mut inverse: Vec<_> = self
+            .pieces
+            .iter()
+            .map(|p| Piece {
+                source: p.image(),
+                offset: -p.offset,
+            })
+            .collect();
+        inverse.sort_by_key(|p| p.source.start);
+        inverse
+    }
+
+    /// All source values that land somewhere in `target`.
+    pub fn preimage(&self, target: Range<usize>) -> IntervalSet<usize> {
+        self.pieces
+            .iter()
+            .filter_map(|p| {
+                let image = p.image();
+                let start = image.start.max(target.start);
+                let end = image.end.min(target.end);
+                (start < end).then(|| Piece::shift(start, -p.offset)..Piece::shift(end, -p.offset))
+            })
+            .collect()
+    }
+}
+
+impl Display for Composed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{:>45} {:>45} {:>12}", "source", "destination", "offset")?;
+        for p in &self.pieces {
+            let image = p.image();
+            writeln!(
+                f,
+                "{:>45} {:>45} {:>+12}",
+                format!("{}..{}", p.source.start, p.source.end),
+                format!("{}..{}", image.start, image.end),
+                p.offset
+            )?;
+        }
+        Ok(())
+    }
 }
 
-struct RangeSet {
-    inner: Vec<Range<usize>>,
+#[derive(Debug, Clone)]
+pub struct Almanac {
+    seeds: Vec<usize>,
+    maps: Vec<Mapping>,
+    /// indices into `maps`, keyed by source category
+    edges: HashMap<String, Vec<usize>>,
 }
 
-impl RangeSet {
-    fn new() -> Self {
-        Self { inner: vec![] }
+impl Almanac {
+    pub fn seeds(&self) -> &[usize] {
+        &self.seeds
+    }
+
+    pub fn categories(&self) -> impl Iterator<Item = &str> {
+        self.maps
+            .iter()
+            .flat_map(|m| [m.from.as_str(), m.to.as_str()])
+            .unique()
+    }
+
+    fn is_category(&self, category: &str) -> bool {
+        self.maps
+            .iter()
+            .any(|m| m.from == category || m.to == category)
     }
 
-    fn insert(&mut self, item: Range<usize>) {
-        let mut overlapping = vec![];
-        for i in 0..self.inner.len() {
-            if item.start < self.inner[i].end && item.end > self.inner[i].start {
-                overlapping.push(i);
+    /// Shortest chain of maps leading from `from` to `to`.
+    fn path(&self, from: &str, to: &str) -> Result<Vec<&Mapping>, AlmanacError> {
+        for c in [from, to] {
+            if !self.is_category(c) {
+                return Err(AlmanacError::UnknownCategory(c.to_string()));
             }
         }
-        let min = item.start.min(
-            overlapping
-                .iter()
-                .map(|&i| self.inner[i].start)
-                .min()
-                .unwrap_or(item.start),
-        );
-        let max = item.end.max(
-            overlapping
-                .iter()
-                .map(|&i| self.inner[i].end)
-                .min()
-                .unwrap_or(item.end),
-        );
+        let mut previous: HashMap<&str, usize> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+        while let Some(current) = queue.pop_front() {
+            if current == to {
+                let mut path = vec![];
+                let mut c = to;
+                while c != from {
+                    let m = &self.maps[previous[c]];
+                    path.push(m);
+                    c = &m.from;
+                }
+                path.reverse();
+                return Ok(path);
+            }
+            for &i in self.edges.get(current).into_iter().flatten() {
+                let next = self.maps[i].to.as_str();
+                if next != from && !previous.contains_key(next) {
+                    previous.insert(next, i);
+                    queue.push_back(next);
+                }
+            }
+        }
+        Err(AlmanacError::Unreachable {
+            from: from.to_string(),
+            to: to.to_string(),
+        })
+    }
 
-        overlapping.iter().for_each(|&i| {
-            self.inner.swap_remove(i);
-        });
+    pub fn map_value(&self, from: &str, to: &str, value: usize) -> Result<usize, AlmanacError> {
+        Ok(self
+            .path(from, to)?
+            .into_iter()
+            .fold(value, |v, m| m.match_item(v)))
+    }
 
-        self.inner.push(min..max);
+    pub fn map_ranges(
+        &self,
+        from: &str,
+        to: &str,
+        ranges: Vec<Range<usize>>,
+    ) -> Result<Vec<Range<usize>>, AlmanacError> {
+        Ok(self.path(from, to)?.into_iter().fold(ranges, |rs, m| {
+            rs.into_iter().flat_map(|r| m.match_range(r)).collect()
+        }))
     }
 
-    fn iter_values(&self) -> impl ParallelIterator<Item = usize> + '_ {
-        self.inner.par_iter().flat_map(|r| r.clone())
+    /// Collapses the chain of maps from `from` to `to` into a single function.
+    pub fn compose(&self, from: &str, to: &str) -> Result<Composed, AlmanacError> {
+        Ok(self
+            .path(from, to)?
+            .into_iter()
+            .fold(Composed::identity(), |c, m| c.then(m)))
     }
-}
 
-impl FromIterator<Range<usize>> for RangeSet {
-    fn from_iter<T: IntoIterator<Item = Range<usize>>>(iter: T) -> Self {
-        let mut rs = Self::new();
-        for i in iter {
-            rs.insert(i);
+    fn check_cycles(&self) -> Result<(), AlmanacError> {
+        // 1 = on the current dfs path, 2 = done
+        fn visit<'a>(
+            almanac: &'a Almanac,
+            category: &'a str,
+            state: &mut HashMap<&'a str, u8>,
+        ) -> Result<(), AlmanacError> {
+            match state.get(category) {
+                Some(1) => return Err(AlmanacError::Cycle(category.to_string())),
+                Some(_) => return Ok(()),
+                None => {}
+            }
+            state.insert(category, 1);
+            for &i in almanac.edges.get(category).into_iter().flatten() {
+                visit(almanac, &almanac.maps[i].to, state)?;
+            }
+            state.insert(category, 2);
+            Ok(())
+        }
+
+        let mut state = HashMap::new();
+        for m in &self.maps {
+            visit(self, &m.from, &mut state)?;
         }
-        rs
+        Ok(())
     }
 }
 
+pub fn part1(input: &Almanac) -> usize {
+    let composed = input.compose("seed", "location").unwrap();
+    input
+        .seeds
+        .iter()
+        .map(|&s| composed.apply(s))
+        .min()
+        .unwrap()
+}
+
 pub fn part2(input: &Almanac) -> usize {
-    let ranges: RangeSet = input
+    let ranges: IntervalSet<usize> = input
         .seeds
         .chunks_exact(2)
-        .map(|s| (s[0]..s[0] + s[1]))
+        .map(|s| s[0]..s[0] + s[1])
         .collect();
 
-    ranges
-        .iter_values()
-        .filter_map(|s| {
-            let mut next = Some(&input.maps);
-            let mut current = s;
-            loop {
-                let (c, n) = next.unwrap().match_item(current);
-                if n.is_none() {
-                    return Some(c);
-                }
-                next = n;
-                current = c;
-            }
-        })
+    input
+        .compose("seed", "location")
+        .unwrap()
+        .image(&ranges)
         .min()
         .unwrap()
 }
 
-fn parse_map<'a>(it: &mut impl Iterator<Item = &'a str>) -> Vec<Map> {
+fn parse_map<'a>(it: &mut impl Iterator<Item = &'a str>) -> Result<Vec<Map>, AlmanacError> {
     it.take_while(|l| !l.is_empty())
         .map(|l| {
-            let mut numbers = l.split_whitespace().flat_map(|n| n.parse::<usize>().ok());
-            let destination = numbers.next().unwrap();
-            let source = numbers.next().unwrap();
-            let count = numbers.next().unwrap();
-            (destination, source, count)
+            let numbers: Vec<usize> = l
+                .split_whitespace()
+                .map(|n| n.parse())
+                .collect::<Result<_, _>>()
+                .map_err(|_| AlmanacError::InvalidEntry(l.to_string()))?;
+            match numbers[..] {
+                [destination, source, count] => Ok((destination, source, count)),
+                _ => Err(AlmanacError::InvalidEntry(l.to_string())),
+            }
         })
         .collect()
 }
 
-pub fn generate(input: &str) -> Almanac {
-    let mut it = input.lines();
-    let seeds: Vec<_> = it
-        .next()
-        .unwrap()
-        .split_whitespace()
-        .skip(1)
-        .flat_map(|s| s.parse::<usize>().ok())
-        .collect();
-    it.next(); // skip empty line
-    let mapname = it.next().unwrap();
-    assert_eq!(mapname, "seed-to-soil map:");
-    let seed_to_soil_map: Vec<_> = parse_map(&mut it);
-
-    //it.next(); // skip empty line
-    let mapname = it.next().unwrap();
-    assert_eq!(mapname, "soil-to-fertilizer map:");
-    let soil_to_fertilizer: Vec<_> = parse_map(&mut it);
-
-    //it.next(); // skip empty line
-    let mapname = it.next().unwrap();
-    assert_eq!(mapname, "fertilizer-to-water map:");
-    let fertilizer_to_water: Vec<_> = parse_map(&mut it);
-
-    //it.next(); // skip empty line
-    let mapname = it.next().unwrap();
-    assert_eq!(mapname, "water-to-light map:");
-    let water_to_light: Vec<_> = parse_map(&mut it);
-
-    //it.next(); // skip empty line
-    let mapname = it.next().unwrap();
-    assert_eq!(mapname, "light-to-temperature map:");
-    let light_to_temperature: Vec<_> = parse_map(&mut it);
-
-    //it.next(); // skip empty line
-    let mapname = it.next().unwrap();
-    assert_eq!(mapname, "temperature-to-humidity map:");
-    let temperature_to_humidity: Vec<_> = parse_map(&mut it);
-
-    //it.next(); // skip empty line
-    let mapname = it.next().unwrap();
-    assert_eq!(mapname, "humidity-to-location map:");
-    let humidity_to_location: Vec<_> = parse_map(&mut it);
-
-    Almanac {
-        seeds,
-        maps: Mapping {
-            map: seed_to_soil_map,
-            next: Some(Box::new(Mapping {
-                map: soil_to_fertilizer,
-                next: Some(Box::new(Mapping {
-                    map: fertilizer_to_water,
-                    next: Some(Box::new(Mapping {
-                        map: water_to_light,
-                        next: Some(Box::new(Mapping {
-                            map: light_to_temperature,
-                            next: Some(Box::new(Mapping {
-                                map: temperature_to_humidity,
-                                next: Some(Box::new(Mapping {
-                                    map: humidity_to_location,
-                                    next: None,
-                                })),
-                            })),
-                        })),
-                    })),
-                })),
-            })),
-        },
+impl FromStr for Almanac {
+    type Err = AlmanacError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut it = s.lines();
+        let line = it.next().unwrap_or_default();
+        let seeds = line
+            .strip_prefix("seeds:")
+            .and_then(|seeds| {
+                seeds
+                    .split_whitespace()
+                    .map(|s| s.parse::<usize>().ok())
+                    .collect::<Option<Vec<_>>>()
+            })
+            .ok_or_else(|| AlmanacError::InvalidSeeds(line.to_string()))?;
+
+        let mut maps: Vec<Mapping> = vec![];
+        let mut edges: HashMap<String, Vec<usize>> = HashMap::new();
+        while let Some(header) = it.next() {
+            if header.is_empty() {
+                continue;
+            }
+            let (from, to) = header
+                .strip_suffix(" map:")
+                .and_then(|name| name.split_once("-to-"))
+                .ok_or_else(|| AlmanacError::InvalidHeader(header.to_string()))?;
+            if maps.iter().any(|m| m.from == from && m.to == to) {
+                return Err(AlmanacError::DuplicateMap {
+                    from: from.to_string(),
+                    to: to.to_string(),
+                });
+            }
+            edges.entry(from.to_string()).or_default().push(maps.len());
+            maps.push(Mapping::new(from, to, parse_map(&mut it)?)?);
+        }
+
+        let almanac = Almanac { seeds, maps, edges };
+        almanac.check_cycles()?;
+        Ok(almanac)
     }
 }
 
+pub fn generate(input: &str) -> Almanac {
+    input.parse().unwrap()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -287,4 +532,259 @@ humidity-to-location map:
 
         assert_eq!(46, part2(&generate(input)))
     }
+
+    fn brute_force(input: &Almanac) -> usize {
+        input
+            .seeds
+            .chunks_exact(2)
+            .flat_map(|s| s[0]..s[0] + s[1])
+            .map(|s| {
+                input
+                    .path("seed", "location")
+                    .unwrap()
+                    .into_iter()
+                    .fold(s, |v, m| m.match_item(v))
+            })
+            .min()
+            .unwrap()
+    }
+
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self, max: usize) -> usize {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (self.0 >> 33) as usize % max
+        }
+    }
+
+    /// non overlapping ranges with random gaps in between, as (start, len)
+    fn random_ranges(rng: &mut Lcg, limit: usize) -> Vec<(usize, usize)> {
+        let mut ranges = vec![];
+        let mut pos = rng.next(50);
+        while pos < limit {
+            let len = 1 + rng.next(100);
+            ranges.push((pos, len));
+            pos += len + rng.next(50);
+        }
+        ranges
+    }
+
+    fn generated_almanac(seed: u64) -> String {
+        let mut rng = Lcg(seed);
+        let seeds = random_ranges(&mut rng, 1000)
+            .into_iter()
+            .map(|(start, len)| format!("{start} {len}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut input = format!("seeds: {seeds}\n");
+        for name in [
+            "seed-to-soil",
+            "soil-to-fertilizer",
+            "fertilizer-to-water",
+            "water-to-light",
+            "light-to-temperature",
+            "temperature-to-humidity",
+            "humidity-to-location",
+        ] {
+            input += &format!("\n{name} map:\n");
+            for (source, len) in random_ranges(&mut rng, 1000) {
+                input += &format!("{} {source} {len}\n", rng.next(1200));
+            }
+        }
+        input
+    }
+
+    #[test]
+    fn test_p2_brute_force() {
+        let input = "seeds: 79 14 55 13
+
+seed-to-soil map:
+50 98 2
+52 50 48
+
+soil-to-fertilizer map:
+0 15 37
+37 52 2
+39 0 15
+
+fertilizer-to-water map:
+49 53 8
+0 11 42
+42 0 7
+57 7 4
+
+water-to-light map:
+88 18 7
+18 25 70
+
+light-to-temperature map:
+45 77 23
+81 45 19
+68 64 13
+
+temperature-to-humidity map:
+0 69 1
+1 0 69
+
+humidity-to-location map:
+60 56 37
+56 93 4";
+        let almanac = generate(input);
+        assert_eq!(brute_force(&almanac), part2(&almanac));
+        let overlapping = generate(&input.replace("79 14 55 13", "79 14 55 13 60 30 50 100 85 3"));
+        assert_eq!(brute_force(&overlapping), part2(&overlapping));
+
+        for seed in 0..20 {
+            let almanac = generate(&generated_almanac(seed));
+            assert_eq!(brute_force(&almanac), part2(&almanac));
+        }
+    }
+
+    #[test]
+    fn test_named_categories() {
+        let input = "seeds: 1 2 3
+
+b-to-c map:
+10 0 5
+
+a-to-b map:
+0 5 5
+5 0 5
+
+c-to-d map:
+0 12 1";
+        let almanac = generate(input);
+
+        assert_eq!(Ok(13), almanac.map_value("a", "c", 8));
+        assert_eq!(Ok(0), almanac.map_value("b", "d", 2));
+        assert_eq!(Ok(8), almanac.map_value("a", "d", 3));
+        assert_eq!(
+            Ok(vec![11..14, 7..9]),
+            almanac.map_ranges("a", "c", vec![6..9, 2..4])
+        );
+        assert_eq!(
+            Err(AlmanacError::UnknownCategory("e".to_string())),
+            almanac.map_value("a", "e", 0)
+        );
+        assert_eq!(
+            Err(AlmanacError::Unreachable {
+                from: "c".to_string(),
+                to: "a".to_string()
+            }),
+            almanac.map_value("c", "a", 0)
+        );
+    }
+
+    #[test]
+    fn test_cycle() {
+        let input = "seeds: 1
+
+a-to-b map:
+0 5 5
+
+b-to-c map:
+0 5 5
+
+c-to-a map:
+0 5 5";
+
+        assert!(matches!(
+            input.parse::<Almanac>(),
+            Err(AlmanacError::Cycle(_))
+        ));
+    }
+
+    #[test]
+    fn test_compose() {
+        let input = "seeds: 1 2 3
+
+b-to-c map:
+10 0 5
+
+a-to-b map:
+0 5 5
+5 0 5";
+        let almanac = generate(input);
+        let composed = almanac.compose("a", "c").unwrap();
+
+        assert_eq!(
+            &[
+                Piece {
+                    source: 0..10,
+                    offset: 5
+                },
+                Piece {
+                    source: 10..usize::MAX,
+                    offset: 0
+                },
+            ][..],
+            composed.pieces()
+        );
+        for v in 0..20 {
+            assert_eq!(almanac.map_value("a", "c", v).unwrap(), composed.apply(v));
+        }
+        // 0..5 -> 5..10 (unmapped in b) and 5..10 -> 0..5 -> 10..15 merge into one piece
+        let ranges = |r: &[Range<usize>]| r.iter().cloned().collect::<IntervalSet<_>>();
+        assert_eq!(ranges(&[7..8, 12..13]), composed.preimage(12..13));
+        assert_eq!(vec![&(2..3)], composed.preimage(7..8).iter().collect_vec());
+        assert!(composed.preimage(3..4).is_empty());
+        assert_eq!(
+            vec![&(5..12)],
+            composed
+                .image(&ranges(&[0..5, 10..12]))
+                .iter()
+                .collect_vec()
+        );
+        assert_eq!(
+            vec![
+                Piece {
+                    source: 5..15,
+                    offset: -5
+                },
+                Piece {
+                    source: 10..usize::MAX,
+                    offset: 0
+                },
+            ],
+            composed.inverse()
+        );
+        assert!(composed.to_string().starts_with(&format!(
+            "{:>45} {:>45} {:>12}\n{:>45} {:>45} {:>12}\n",
+            "source", "destination", "offset", "0..10", "5..15", "+5"
+        )));
+    }
+
+    #[test]
+    fn test_compose_brute_force() {
+        for seed in 0..20 {
+            let almanac = generate(&generated_almanac(seed));
+            let composed = almanac.compose("seed", "location").unwrap();
+            for v in 0..1200 {
+                let location = almanac.map_value("seed", "location", v).unwrap();
+                assert_eq!(location, composed.apply(v));
+                assert!(composed.preimage(location..location + 1).contains(v));
+            }
+        }
+    }
+
+    #[test]
+    fn test_overlapping_entries() {
+        let input = "seeds: 1
+
+a-to-b map:
+0 5 5
+20 0 6";
+
+        assert_eq!(
+            Err(AlmanacError::OverlappingEntries {
+                from: "a".to_string(),
+                to: "b".to_string()
+            }),
+            input.parse::<Almanac>().map(|_| ())
+        );
+    }
 }
@@ -1,13 +1,20 @@
-use std::iter::zip;
+use num_bigint::BigUint;
+use std::{fmt::Debug, iter::zip, ops::RangeInclusive};
 
-#[derive(Debug, Clone, Copy)]
-pub struct Race {
-    time: usize,
-    distance: usize,
+#[derive(Debug, Clone)]
+pub struct Race<T = BigUint> {
+    time: T,
+    distance: T,
+}
+
+impl<T> Race<T> {
+    pub fn new(time: T, distance: T) -> Self {
+        Self { time, distance }
+    }
 }
 
 pub fn generate(input: &str) -> Vec<Race> {
-    let lines: Vec<Vec<usize>> = input
+    let lines: Vec<Vec<BigUint>> = input
         .lines()
         .map(|l| {
             l.split_once(':')
@@ -19,29 +26,65 @@ pub fn generate(input: &str) -> Vec<Race> {
         })
         .collect();
     zip(lines[0].iter(), lines[1].iter())
-        .map(|(&time, &distance)| Race { time, distance })
+        .map(|(time, distance)| Race::new(time.clone(), distance.clone()))
         .collect()
 }
 
 // formula final distance:
 // t * (max_t - t) > d
-// t * max_t - t * t > d
-// max_t -t > d / t
-pub fn part1(input: &[Race]) -> usize {
-    input
-        .iter()
-        .map(|r| {
-            for n in 0..r.time / 2 {
-                if n * (r.time - n) > r.distance {
-                    return r.time - n - n + 1;
-                }
-            }
-            1
-        })
-        .product()
+// t^2 - max_t * t + d < 0
+// t = (max_t +- sqrt(max_t^2 - 4d)) / 2
+fn solve(time: &BigUint, distance: &BigUint) -> Option<(BigUint, BigUint)> {
+    let wins = |hold: &BigUint| hold * (time - hold) > *distance;
+
+    let square = time * time;
+    let four_d = distance * 4u32;
+    if square <= four_d {
+        return None;
+    }
+    // floor of the lower root, off by at most one from the first winning hold
+    let mut low = (time - (square - four_d).sqrt()) / 2u32;
+    while low > BigUint::from(0u32) && wins(&(&low - 1u32)) {
+        low -= 1u32;
+    }
+    while low <= time / 2u32 && !wins(&low) {
+        low += 1u32;
+    }
+    if !wins(&low) {
+        return None;
+    }
+    let high = time - &low;
+    Some((low, high))
+}
+
+/// The hold times beating the record, `None` if the record can't be beaten.
+/// The math happens on `BigUint` so squaring the time never overflows.
+pub fn winning_holds<T>(race: &Race<T>) -> Option<RangeInclusive<T>>
+where
+    T: Clone + Into<BigUint> + TryFrom<BigUint>,
+    T::Error: Debug,
+{
+    let (low, high) = solve(&race.time.clone().into(), &race.distance.clone().into())?;
+    // both are at most `time`, so they fit into T
+    Some(T::try_from(low).unwrap()..=T::try_from(high).unwrap())
 }
 
-pub fn part2(input: &[Race]) -> usize {
+pub fn ways_to_win<T>(race: &Race<T>) -> T
+where
+    T: Clone + Into<BigUint> + TryFrom<BigUint>,
+    T::Error: Debug,
+{
+    let count = solve(&race.time.clone().into(), &race.distance.clone().into())
+        .map(|(low, high)| high - low + 1u32)
+        .unwrap_or_default();
+    T::try_from(count).unwrap()
+}
+
+pub fn part1(input: &[Race]) -> BigUint {
+    input.iter().map(ways_to_win).product()
+}
+
+pub fn part2(input: &[Race]) -> BigUint {
     let (t, d) = input
         .iter()
         .fold((String::new(), String::new()), |(mut t, mut d), r| {
@@ -49,10 +92,7 @@ pub fn part2(input: &[Race]) -> usize {
             d += &r.distance.to_string();
             (t, d)
         });
-    part1(&[Race {
-        time: t.parse().unwrap(),
-        distance: d.parse().unwrap(),
-    }])
+    ways_to_win(&Race::new(t.parse().unwrap(), d.parse().unwrap()))
 }
 
 #[cfg(test)]
@@ -64,13 +104,50 @@ mod test {
         let input = "Time:      7  15   30
 Distance:  9  40  200";
 
-        assert_eq!(288, part1(&generate(input)));
+        assert_eq!(BigUint::from(288u32), part1(&generate(input)));
     }
     #[test]
     fn test_p2() {
         let input = "Time:      7  15   30
 Distance:  9  40  200";
 
-        assert_eq!(71503, part2(&generate(input)));
+        assert_eq!(BigUint::from(71503u32), part2(&generate(input)));
+    }
+
+    #[test]
+    fn test_winning_holds() {
+        assert_eq!(Some(2..=5), winning_holds(&Race::new(7u128, 9)));
+        assert_eq!(Some(11..=19), winning_holds(&Race::new(30u128, 200)));
+        // holding 5 exactly matches the record which doesn't count
+        assert_eq!(None, winning_holds(&Race::new(10u128, 25)));
+        assert_eq!(Some(5..=5), winning_holds(&Race::new(10u128, 24)));
+        assert_eq!(None, winning_holds(&Race::new(0u128, 0)));
+        assert_eq!(3, ways_to_win(&Race::new(12u128, 32)));
+        assert_eq!(Some(5..=7), winning_holds(&Race::new(12u128, 32)));
+
+        for time in 0..60u128 {
+            for distance in 0..time * time / 4 + 2 {
+                let brute: Vec<_> = (0..=time).filter(|h| h * (time - h) > distance).collect();
+                let holds = winning_holds(&Race::new(time, distance));
+                assert_eq!(brute.first().zip(brute.last()).map(|(&l, &h)| l..=h), holds);
+            }
+        }
+    }
+
+    #[test]
+    fn test_large() {
+        let time = u128::MAX >> 2;
+        let race = Race::new(time, time);
+        assert_eq!(Some(2..=time - 2), winning_holds(&race));
+
+        let time: BigUint = "123456789012345678901234567890123456789012"
+            .parse()
+            .unwrap();
+        let hold = BigUint::from(1000u32);
+        let distance = hold.clone() * (time.clone() - hold.clone());
+        assert_eq!(
+            Some(hold.clone() + 1u32..=time.clone() - hold.clone() - 1u32),
+            winning_holds(&Race::new(time, distance))
+        );
     }
 }
@@ -1,5 +1,8 @@
-use std::cmp::Ordering;
-use std::collections::HashMap;
+use itertools::Itertools;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Card {
@@ -18,6 +21,24 @@ pub enum Card {
     A,
 }
 
+impl Card {
+    pub const ALL: [Card; 13] = [
+        Card::Two,
+        Card::Three,
+        Card::Four,
+        Card::Five,
+        Card::Six,
+        Card::Seven,
+        Card::Eight,
+        Card::Nine,
+        Card::T,
+        Card::J,
+        Card::Q,
+        Card::K,
+        Card::A,
+    ];
+}
+
 impl From<char> for Card {
     fn from(value: char) -> Self {
         use Card as C;
@@ -40,36 +61,116 @@ impl From<char> for Card {
     }
 }
 
-type Hand = [Card; 5];
+impl From<Card> for char {
+    fn from(value: Card) -> Self {
+        "23456789TJQKA".as_bytes()[value as usize] as char
+    }
+}
+
+pub type Hand = Vec<Card>;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub enum HandType<T> {
-    HighCard(T),
-    OnePair(T),
-    TwoPair(T),
-    ThreeOfAKind(T),
-    FullHouse(T),
-    FourOfAKind(T),
-    FiveOfAKind(T),
-}
-
-impl From<Hand> for HandType<Hand> {
-    fn from(value: Hand) -> Self {
+pub enum HandType {
+    HighCard,
+    OnePair,
+    TwoPair,
+    ThreeOfAKind,
+    FullHouse,
+    FourOfAKind,
+    FiveOfAKind,
+}
+
+impl HandType {
+    pub const ALL: [HandType; 7] = [
+        HandType::HighCard,
+        HandType::OnePair,
+        HandType::TwoPair,
+        HandType::ThreeOfAKind,
+        HandType::FullHouse,
+        HandType::FourOfAKind,
+        HandType::FiveOfAKind,
+    ];
+
+    /// Classifies by the sizes of the two largest groups of equal cards.
+    fn from_groups(largest: usize, second: usize) -> Self {
         use HandType as HT;
-        let mut hm = HashMap::new();
-        for v in value {
-            hm.entry(v).and_modify(|c| *c += 1).or_insert(1);
+        match (largest, second) {
+            (5.., _) => HT::FiveOfAKind,
+            (4, _) => HT::FourOfAKind,
+            (3, 2..) => HT::FullHouse,
+            (3, _) => HT::ThreeOfAKind,
+            (2, 2..) => HT::TwoPair,
+            (2, _) => HT::OnePair,
+            _ => HT::HighCard,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Rules {
+    /// cards that can stand in for any other card
+    pub wild: Vec<Card>,
+    /// every card from weakest to strongest, used to break ties
+    pub order: Vec<Card>,
+    pub hand_size: usize,
+}
+
+impl Rules {
+    pub fn standard() -> Self {
+        Self {
+            wild: vec![],
+            order: Card::ALL.to_vec(),
+            hand_size: 5,
         }
-        match hm.len() {
-            5 => HT::HighCard(value),
-            4 => HT::OnePair(value),
-            3 if hm.values().max() == Some(&2) => HT::TwoPair(value),
-            3 if hm.values().max() == Some(&3) => HT::ThreeOfAKind(value),
-            2 if hm.values().max() == Some(&3) => HT::FullHouse(value),
-            2 if hm.values().max() == Some(&4) => HT::FourOfAKind(value),
-            1 => HT::FiveOfAKind(value),
-            _ => panic!("Cannot have more than 5 unique values in a hand"),
+    }
+
+    /// J is a joker and the weakest card
+    pub fn jokers() -> Self {
+        let mut order = vec![Card::J];
+        order.extend(Card::ALL.into_iter().filter(|&c| c != Card::J));
+        Self {
+            wild: vec![Card::J],
+            order,
+            hand_size: 5,
+        }
+    }
+
+    pub fn is_wild(&self, card: Card) -> bool {
+        self.wild.contains(&card)
+    }
+
+    fn rank(&self, card: Card) -> usize {
+        self.order
+            .iter()
+            .position(|&c| c == card)
+            .expect("card to be part of the order")
+    }
+
+    pub fn classify(&self, hand: &[Card]) -> HandType {
+        assert_eq!(hand.len(), self.hand_size, "Invalid hand size");
+        let mut hm = HashMap::new();
+        let mut wild = 0;
+        for &c in hand {
+            if self.is_wild(c) {
+                wild += 1;
+            } else {
+                *hm.entry(c).or_insert(0) += 1;
+            }
         }
+        let mut groups: Vec<usize> = hm.into_values().collect();
+        groups.sort_unstable_by(|a, b| b.cmp(a));
+        // wild cards always do best joining the largest group
+        let largest = groups.first().copied().unwrap_or(0) + wild;
+        let second = groups.get(1).copied().unwrap_or(0);
+        HandType::from_groups(largest, second)
+    }
+
+    /// Sort key for a hand, the type first and then each card's rank in order.
+    pub fn strength(&self, hand: &[Card]) -> (HandType, Vec<usize>) {
+        (
+            self.classify(hand),
+            hand.iter().map(|&c| self.rank(c)).collect(),
+        )
     }
 }
 
@@ -77,23 +178,15 @@ pub fn generate(input: &str) -> Vec<(Hand, usize)> {
     let mut v = vec![];
     for l in input.lines().filter(|l| !l.is_empty()) {
         let (hs, bids) = l.split_once(' ').unwrap();
-        v.push((
-            hs.chars()
-                .map(From::from)
-                .collect::<Vec<_>>()
-                .try_into()
-                .unwrap(),
-            bids.parse().unwrap(),
-        ));
+        v.push((hs.chars().map(From::from).collect(), bids.parse().unwrap()));
     }
     v
 }
 
-pub fn part1(input: &[(Hand, usize)]) -> usize {
-    let mut input: Vec<(HandType<Hand>, usize)> =
-        input.iter().map(|&(h, b)| (h.into(), b)).collect();
+pub fn winnings(input: &[(Hand, usize)], rules: &Rules) -> usize {
+    let mut input: Vec<_> = input.iter().map(|(h, b)| (rules.strength(h), b)).collect();
 
-    input.sort_by_key(|v| v.0);
+    input.sort_by(|a, b| a.0.cmp(&b.0));
 
     input
         .into_iter()
@@ -102,100 +195,88 @@ pub fn part1(input: &[(Hand, usize)]) -> usize {
         .sum()
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct P2Card(Card);
+pub fn part1(input: &[(Hand, usize)]) -> usize {
+    winnings(input, &Rules::standard())
+}
 
-impl Ord for P2Card {
-    fn cmp(&self, other: &Self) -> Ordering {
-        if self.0 == other.0 {
-            Ordering::Equal
-        } else if self.0 == Card::J {
-            Ordering::Less
-        } else if other.0 == Card::J {
-            Ordering::Greater
-        } else {
-            self.0.cmp(&other.0)
-        }
-    }
+pub fn part2(input: &[(Hand, usize)]) -> usize {
+    winnings(input, &Rules::jokers())
 }
 
-impl PartialOrd for P2Card {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+/// Replaces every wild card so that the hand reaches its best type.
+/// The wild cards all join the largest group, ties go to the stronger card.
+pub fn best_substitution(rules: &Rules, hand: &[Card]) -> (Hand, HandType) {
+    let mut hm = HashMap::new();
+    for &c in hand.iter().filter(|&&c| !rules.is_wild(c)) {
+        *hm.entry(c).or_insert(0) += 1;
     }
+    let target = hm
+        .into_iter()
+        .max_by_key(|&(c, count)| (count, rules.rank(c)))
+        .map(|(c, _)| c)
+        .unwrap_or_else(|| *rules.order.last().unwrap());
+    let hand: Hand = hand
+        .iter()
+        .map(|&c| if rules.is_wild(c) { target } else { c })
+        .collect();
+    let plain = Rules {
+        wild: vec![],
+        ..rules.clone()
+    };
+    let hand_type = plain.classify(&hand);
+    (hand, hand_type)
 }
-type P2Hand = [P2Card; 5];
 
-fn convert_to_p2(value: Hand) -> P2Hand {
-    [
-        P2Card(value[0]),
-        P2Card(value[1]),
-        P2Card(value[2]),
-        P2Card(value[3]),
-        P2Card(value[4]),
-    ]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Distribution {
+    /// number of ordered hands per type
+    pub counts: BTreeMap<HandType, usize>,
+    pub total: usize,
 }
 
-impl From<P2Hand> for HandType<P2Hand> {
-    fn from(value: P2Hand) -> Self {
-        use HandType as HT;
-        let mut hm = HashMap::new();
-        for v in value {
-            hm.entry(v).and_modify(|c| *c += 1).or_insert(1);
-        }
-        let len = if hm.contains_key(&P2Card(Card::J)) {
-            hm.len() - 1
-        } else {
-            hm.len()
-        };
-        match len {
-            5 => HT::HighCard(value),        // No joker here
-            4 => HT::OnePair(value),         // maybe one joker, or maybe none, still only 1 pair
-            0 | 1 => HT::FiveOfAKind(value), // if 0, all are jokers, if 1 it might be 5 of a
-            // different kind, OR one kind of a different card and the rest jokers
-            3 if hm.contains_key(&P2Card(Card::J)) => HT::ThreeOfAKind(value), // eihter one or 2
-            // jokers, makes this a 3 of a kind regardless (3 +1 joker means at least on other
-            // card has to double up)
-            3 if hm.values().max() == Some(&2) => HT::TwoPair(value), // No joker here and the next
-            // one
-            3 if hm.values().max() == Some(&3) => HT::ThreeOfAKind(value),
-            // if I have 2 uniques and at least 1 joker, the only way to get a full house is if
-            // both of the other uniques double up, eg: J AA KK
-            // otherwise this will always be a FourOfAKind
-            // eg: J A KKK, JJ A KK, JJJ A K (Pretend J is K)
-            2 if hm.contains_key(&P2Card(Card::J)) => {
-                let lowest_non_joker_count = *hm
-                    .iter()
-                    .filter_map(|(k, v)| if k == &P2Card(Card::J) { None } else { Some(v) })
-                    .min()
-                    .unwrap();
-                if lowest_non_joker_count == 1 {
-                    HT::FourOfAKind(value)
-                } else {
-                    HT::FullHouse(value)
-                }
-            }
-            // No Jokers here, solve as in Part1
-            2 if hm.values().max() == Some(&3) => HT::FullHouse(value),
-            2 if hm.values().max() == Some(&4) => HT::FourOfAKind(value),
-            _ => panic!("Cannot have more than 5 unique values in a hand: {hm:?}"),
-        }
+impl Distribution {
+    pub fn probability(&self, hand_type: HandType) -> f64 {
+        self.counts.get(&hand_type).copied().unwrap_or(0) as f64 / self.total as f64
     }
 }
 
-pub fn part2(input: &[(Hand, usize)]) -> usize {
-    let mut input: Vec<(HandType<P2Hand>, usize)> = input
-        .iter()
-        .map(|&(h, b)| (convert_to_p2(h).into(), b))
-        .collect();
-
-    input.sort_by_key(|v| v.0);
+impl Display for Distribution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{:<14} {:>12} {:>12}", "type", "hands", "probability")?;
+        for ht in HandType::ALL {
+            writeln!(
+                f,
+                "{:<14} {:>12} {:>12.8}",
+                format!("{ht:?}"),
+                self.counts.get(&ht).copied().unwrap_or(0),
+                self.probability(ht)
+            )?;
+        }
+        writeln!(f, "{:<14} {:>12}", "total", self.total)
+    }
+}
 
-    input
+/// Exact distribution of hand types over every ordered hand of `rules.hand_size` cards.
+/// Each multiset of cards is classified once and weighted by its number of orderings.
+pub fn type_distribution(rules: &Rules) -> Distribution {
+    let factorial = |n: usize| (1..=n).product::<usize>();
+    let mut counts = BTreeMap::new();
+    for hand in Card::ALL
         .into_iter()
-        .enumerate()
-        .map(|(i, (_, b))| b * (i + 1))
-        .sum()
+        .combinations_with_replacement(rules.hand_size)
+    {
+        let orderings = factorial(rules.hand_size)
+            / hand
+                .iter()
+                .dedup_with_count()
+                .map(|(n, _)| factorial(n))
+                .product::<usize>();
+        *counts.entry(rules.classify(&hand)).or_insert(0) += orderings;
+    }
+    Distribution {
+        counts,
+        total: Card::ALL.len().pow(rules.hand_size as u32),
+    }
 }
 
 #[cfg(test)]
@@ -222,4 +303,148 @@ QQQJA 483";
 
         assert_eq!(5905, part2(&generate(input)));
     }
+
+    #[test]
+    fn test_hand_size() {
+        let rules = Rules {
+            hand_size: 3,
+            ..Rules::standard()
+        };
+        let input = "2A2 10
+KKK 100
+A34 1";
+
+        assert_eq!(1 + 2 * 10 + 3 * 100, winnings(&generate(input), &rules));
+    }
+
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self, max: usize) -> usize {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (self.0 >> 33) as usize % max
+        }
+    }
+
+    /// best type reachable by replacing every wild card with any real card
+    fn brute_force_best(rules: &Rules, hand: &[Card]) -> HandType {
+        let plain = Rules {
+            wild: vec![],
+            ..rules.clone()
+        };
+        let wild: Vec<_> = (0..hand.len())
+            .filter(|&i| rules.is_wild(hand[i]))
+            .collect();
+        let mut best = HandType::HighCard;
+        let mut hand = hand.to_vec();
+        for choice in 0..Card::ALL.len().pow(wild.len() as u32) {
+            let mut choice = choice;
+            for &i in &wild {
+                hand[i] = Card::ALL[choice % Card::ALL.len()];
+                choice /= Card::ALL.len();
+            }
+            best = best.max(plain.classify(&hand));
+        }
+        best
+    }
+
+    #[test]
+    fn test_wild_picks_best() {
+        let mut rng = Lcg(7);
+        for rules in [
+            Rules::jokers(),
+            Rules {
+                wild: vec![Card::Two, Card::Q],
+                ..Rules::standard()
+            },
+            Rules {
+                wild: vec![Card::A],
+                hand_size: 4,
+                ..Rules::standard()
+            },
+        ] {
+            for _ in 0..500 {
+                let hand: Hand = (0..rules.hand_size)
+                    .map(|_| Card::ALL[rng.next(Card::ALL.len())])
+                    .collect();
+                assert_eq!(
+                    brute_force_best(&rules, &hand),
+                    rules.classify(&hand),
+                    "{hand:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_best_substitution() {
+        let rules = Rules::jokers();
+        let hand: Hand = "KTJJT".chars().map(Card::from).collect();
+        let expected: Hand = "KTTTT".chars().map(Card::from).collect();
+        assert_eq!(
+            (expected, HandType::FourOfAKind),
+            best_substitution(&rules, &hand)
+        );
+
+        let hand: Hand = "2J3K2".chars().map(Card::from).collect();
+        let expected: Hand = "223K2".chars().map(Card::from).collect();
+        assert_eq!(
+            (expected, HandType::ThreeOfAKind),
+            best_substitution(&rules, &hand)
+        );
+        // ties go to the stronger card
+        let hand: Hand = "2JKK2".chars().map(Card::from).collect();
+        let expected: Hand = "2KKK2".chars().map(Card::from).collect();
+        assert_eq!(
+            (expected, HandType::FullHouse),
+            best_substitution(&rules, &hand)
+        );
+
+        let hand: Hand = "JJJJJ".chars().map(Card::from).collect();
+        let expected: Hand = "AAAAA".chars().map(Card::from).collect();
+        assert_eq!(
+            (expected, HandType::FiveOfAKind),
+            best_substitution(&rules, &hand)
+        );
+    }
+
+    #[test]
+    fn test_type_distribution() {
+        let standard = type_distribution(&Rules::standard());
+        assert_eq!(371293, standard.total);
+        assert_eq!(371293, standard.counts.values().sum::<usize>());
+        assert_eq!(Some(&13), standard.counts.get(&HandType::FiveOfAKind));
+        // 13 ranks, 12 kickers, 5 positions for the kicker
+        assert_eq!(
+            Some(&(13 * 12 * 5)),
+            standard.counts.get(&HandType::FourOfAKind)
+        );
+        assert_eq!(
+            Some(&(13 * 12 * 11 * 10 * 9)),
+            standard.counts.get(&HandType::HighCard)
+        );
+
+        let jokers = type_distribution(&Rules::jokers());
+        assert_eq!(371293, jokers.counts.values().sum::<usize>());
+        assert!(
+            jokers.probability(HandType::FiveOfAKind) > standard.probability(HandType::FiveOfAKind)
+        );
+
+        // matches classifying every ordered hand on its own
+        let small = Rules {
+            hand_size: 3,
+            ..Rules::jokers()
+        };
+        let mut counts = BTreeMap::new();
+        for hand in (0..small.hand_size)
+            .map(|_| Card::ALL)
+            .multi_cartesian_product()
+        {
+            *counts.entry(small.classify(&hand)).or_insert(0) += 1;
+        }
+        assert_eq!(counts, type_distribution(&small).counts);
+    }
 }
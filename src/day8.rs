@@ -1,4 +1,5 @@
-use std::collections::HashMap;
+use itertools::Itertools;
+use std::{collections::HashMap, fmt::Write};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Direction {
@@ -6,23 +7,80 @@ pub enum Direction {
     Right,
 }
 
+/// A network with every node interned into a dense id.
 pub struct Map<'a> {
     directions: Vec<Direction>,
-    nodes: HashMap<&'a str, (&'a str, &'a str)>,
+    names: Vec<&'a str>,
+    ids: HashMap<&'a str, usize>,
+    left: Vec<usize>,
+    right: Vec<usize>,
+    /// `jumps[k][n]` is where node `n` ends up after `2^k` full passes over the directions
+    jumps: Vec<Vec<usize>>,
 }
 
-impl Map<'_> {
-    fn next(&self, direction: Direction, current: &str) -> &str {
-        let (l, r) = self.nodes.get(current).unwrap();
-
+impl<'a> Map<'a> {
+    fn next(&self, direction: Direction, current: usize) -> usize {
         match direction {
-            Direction::Left => l,
-            Direction::Right => r,
+            Direction::Left => self.left[current],
+            Direction::Right => self.right[current],
+        }
+    }
+
+    pub fn id(&self, name: &str) -> Option<usize> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn name(&self, id: usize) -> &'a str {
+        self.names[id]
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// The node reached after `steps` steps from `start`.
+    /// Full passes over the directions are done by binary lifting, so this takes
+    /// `O(log steps + directions)` time.
+    pub fn position_after(&self, start: &str, steps: u64) -> Option<&'a str> {
+        let mut current = self.id(start)?;
+        let len = self.directions.len() as u64;
+        let mut passes = steps / len;
+        let mut level = 0;
+        while passes > 0 {
+            if passes & 1 == 1 {
+                current = self.jumps[level][current];
+            }
+            passes >>= 1;
+            level += 1;
+        }
+        for &d in &self.directions[..(steps % len) as usize] {
+            current = self.next(d, current);
+        }
+        Some(self.name(current))
+    }
+
+    fn build_jumps(&mut self) {
+        let pass: Vec<_> = (0..self.len())
+            .map(|n| {
+                self.directions
+                    .iter()
+                    .fold(n, |current, &d| self.next(d, current))
+            })
+            .collect();
+        self.jumps = vec![pass];
+        for _ in 1..u64::BITS {
+            let last = self.jumps.last().unwrap();
+            let next = last.iter().map(|&n| last[n]).collect();
+            self.jumps.push(next);
         }
     }
 }
 
-pub fn generate(input: &str) -> Map {
+pub fn generate(input: &str) -> Map<'_> {
     let mut it = input.lines();
     let directions = it
         .next()
@@ -36,7 +94,7 @@ pub fn generate(input: &str) -> Map {
         .collect();
 
     it.next().unwrap(); // empty line;
-    let nodes = it
+    let nodes: Vec<_> = it
         .map(|s| {
             let (key, tuple) = s.split_once(" = ").unwrap();
             let tuple = tuple
@@ -47,18 +105,32 @@ pub fn generate(input: &str) -> Map {
         })
         .collect();
 
-    Map { directions, nodes }
+    let names: Vec<_> = nodes.iter().map(|&(key, _)| key).collect();
+    let ids: HashMap<_, _> = names.iter().enumerate().map(|(i, &n)| (n, i)).collect();
+    let (left, right) = nodes.iter().map(|(_, (l, r))| (ids[l], ids[r])).unzip();
+
+    let mut map = Map {
+        directions,
+        names,
+        ids,
+        left,
+        right,
+        jumps: vec![],
+    };
+    map.build_jumps();
+    map
 }
 
 pub fn part1(input: &Map) -> usize {
+    let end = input.id("ZZZ").unwrap();
     input
         .directions
         .iter()
         .cycle()
-        .scan("AAA", |state, &d| {
-            *state = input.next(d, state);
+        .scan(input.id("AAA").unwrap(), |state, &d| {
+            *state = input.next(d, *state);
 
-            if *state == "ZZZ" {
+            if *state == end {
                 None
             } else {
                 Some(1)
@@ -68,60 +140,188 @@ pub fn part1(input: &Map) -> usize {
         + 1
 }
 
-// it would be smart to store the state somewhere, so we dont have to recalculate it every
-// iteration
-// stolen from num crate
-fn gcd(a: usize, b: usize) -> usize {
-    // Use Stein's algorithm
-    let mut m = a;
-    let mut n = b;
-    if m == 0 || n == 0 {
-        return m | n;
-    }
-
-    // find common factors of 2
-    let shift = (m | n).trailing_zeros();
-
-    // divide n and m by 2 until odd
-    m >>= m.trailing_zeros();
-    n >>= n.trailing_zeros();
+/// Where a single ghost walks, as a prefix followed by a cycle over
+/// `(node, instruction index)` states. Times are counted in steps from the start.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GhostCycle {
+    /// first time the ghost is in a state of the cycle
+    pub offset: usize,
+    pub length: usize,
+    /// times before `offset` the ghost stands on a `..Z` node
+    pub pre_cycle_hits: Vec<usize>,
+    /// times in `offset..offset + length` the ghost stands on a `..Z` node,
+    /// these repeat every `length` steps
+    pub cycle_hits: Vec<usize>,
+}
 
-    while m != n {
-        if m > n {
-            m -= n;
-            m >>= m.trailing_zeros();
+impl GhostCycle {
+    fn contains(&self, time: usize) -> bool {
+        if time < self.offset {
+            self.pre_cycle_hits.contains(&time)
         } else {
-            n -= m;
-            n >>= n.trailing_zeros();
+            let time = self.offset + (time - self.offset) % self.length;
+            self.cycle_hits.contains(&time)
+        }
+    }
+}
+
+pub fn ghost_cycle(input: &Map, start: &str) -> GhostCycle {
+    let len = input.directions.len();
+    // first time each (node, instruction index) state was seen
+    let mut seen = vec![None; input.len() * len];
+    let mut hits = vec![];
+    let mut current = input.id(start).unwrap();
+    let mut time = 0;
+    loop {
+        let i = time % len;
+        if let Some(offset) = seen[current * len + i] {
+            let (pre_cycle_hits, cycle_hits) = hits.into_iter().partition(|&t| t < offset);
+            return GhostCycle {
+                offset,
+                length: time - offset,
+                pre_cycle_hits,
+                cycle_hits,
+            };
         }
+        seen[current * len + i] = Some(time);
+        if input.name(current).ends_with('Z') {
+            hits.push(time);
+        }
+        current = input.next(input.directions[i], current);
+        time += 1;
     }
-    m << shift
 }
 
-fn lcm(a: usize, b: usize) -> usize {
-    if a == 0 && b == 0 {
-        0
+fn ext_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
     } else {
-        a * (b / gcd(a, b))
+        let (g, x, y) = ext_gcd(b, a % b);
+        (g, y, x - (a / b) * y)
+    }
+}
+
+/// Merges `t = a mod m` with `t = b mod n`, `None` if they contradict.
+/// The moduli don't have to be coprime.
+fn crt((a, m): (i128, i128), (b, n): (i128, i128)) -> Option<(i128, i128)> {
+    let (g, p, _) = ext_gcd(m, n);
+    if (b - a) % g != 0 {
+        return None;
     }
+    let l = m / g * n;
+    let k = ((b - a) / g * p).rem_euclid(n / g);
+    Some(((a + m * k).rem_euclid(l), l))
 }
+
+/// First time after the start every ghost stands on a `..Z` node at once.
+pub fn first_common_hit(ghosts: &[GhostCycle]) -> Option<usize> {
+    let (first, rest) = ghosts.split_first()?;
+    // before every ghost is inside its cycle, check the first ghost's hits one by one
+    let settled = ghosts.iter().map(|g| g.offset).max().unwrap();
+    let early = first
+        .pre_cycle_hits
+        .iter()
+        .copied()
+        .chain((first.offset..settled).filter(|&t| first.contains(t)))
+        .find(|&t| t > 0 && rest.iter().all(|g| g.contains(t)));
+    if early.is_some() {
+        return early;
+    }
+
+    // afterwards every ghost only restricts the time to some residues
+    let mut residues = vec![(0i128, 1i128)];
+    for g in ghosts {
+        residues = residues
+            .into_iter()
+            .cartesian_product(&g.cycle_hits)
+            .filter_map(|(r, &h)| crt(r, (h as i128, g.length as i128)))
+            .unique()
+            .collect();
+    }
+    let settled = settled.max(1) as i128;
+    residues
+        .into_iter()
+        .map(|(r, l)| r + (settled - r + l - 1).div_euclid(l) * l)
+        .min()
+        .map(|t| t as usize)
+}
+
 pub fn part2(input: &Map) -> usize {
-    let mut starting: Vec<_> = input
-        .nodes
-        .keys()
-        .filter_map(|&k| if k.ends_with('A') { Some((k, 0)) } else { None })
+    let ghosts: Vec<_> = input
+        .names
+        .iter()
+        .filter(|k| k.ends_with('A'))
+        .map(|k| ghost_cycle(input, k))
         .collect();
+    first_common_hit(&ghosts).unwrap()
+}
+
+/// Nodes visited walking `steps` steps from `start`, including `start` itself.
+pub fn walk<'a>(input: &Map<'a>, start: &str, steps: usize) -> Vec<&'a str> {
+    let mut current = input.id(start).unwrap();
+    let mut path = vec![input.name(current)];
+    for &d in input.directions.iter().cycle().take(steps) {
+        current = input.next(d, current);
+        path.push(input.name(current));
+    }
+    path
+}
+
+/// The path `part1` takes from `AAA` to `ZZZ`.
+pub fn part1_path<'a>(input: &Map<'a>) -> Vec<&'a str> {
+    walk(input, "AAA", part1(input))
+}
 
-    for &d in input.directions.iter().cycle() {
-        for (n, c) in starting.iter_mut().filter(|(k, _)| !k.ends_with('Z')) {
-            *n = input.next(d, n);
-            *c += 1;
+/// The path of every ghost up to the point where it starts repeating itself.
+pub fn ghost_paths<'a>(input: &Map<'a>) -> Vec<Vec<&'a str>> {
+    input
+        .names
+        .iter()
+        .filter(|k| k.ends_with('A'))
+        .map(|k| {
+            let cycle = ghost_cycle(input, k);
+            walk(input, k, cycle.offset + cycle.length)
+        })
+        .collect()
+}
+
+const PATH_COLORS: [&str; 6] = ["blue", "red", "darkgreen", "orange", "purple", "brown"];
+
+/// Renders the network as a Graphviz DOT graph. `..A` nodes are green, `..Z` nodes
+/// red, and the edges along each of `paths` are drawn bold in their own color.
+pub fn to_dot(input: &Map, paths: &[Vec<&str>]) -> String {
+    let mut highlighted = HashMap::new();
+    for (i, path) in paths.iter().enumerate() {
+        for (from, to) in path.iter().tuple_windows() {
+            highlighted
+                .entry((*from, *to))
+                .or_insert(PATH_COLORS[i % PATH_COLORS.len()]);
+        }
+    }
+
+    let mut dot = String::from("digraph network {\n");
+    for (id, name) in input.names.iter().enumerate() {
+        if name.ends_with('A') {
+            writeln!(dot, "    \"{name}\" [style=filled, fillcolor=palegreen];").unwrap();
+        } else if name.ends_with('Z') {
+            writeln!(dot, "    \"{name}\" [style=filled, fillcolor=lightcoral];").unwrap();
         }
-        if starting.iter().all(|(n, _)| n.ends_with('Z')) {
-            break;
+        let (left, right) = (input.name(input.left[id]), input.name(input.right[id]));
+        let edges = if left == right {
+            vec![("LR", left)]
+        } else {
+            vec![("L", left), ("R", right)]
+        };
+        for (label, to) in edges {
+            write!(dot, "    \"{name}\" -> \"{to}\" [label=\"{label}\"").unwrap();
+            if let Some(color) = highlighted.get(&(*name, to)) {
+                write!(dot, ", color={color}, penwidth=3").unwrap();
+            }
+            writeln!(dot, "];").unwrap();
         }
     }
-    starting.into_iter().fold(1, |a, (_, b)| lcm(a, b))
+    dot.push_str("}\n");
+    dot
 }
 
 #[cfg(test)]
@@ -168,4 +368,187 @@ XXX = (XXX, XXX)";
 
         assert_eq!(6, part2(&generate(input)));
     }
+
+    fn brute_force(input: &Map) -> usize {
+        let mut ghosts: Vec<_> = (0..input.len())
+            .filter(|&k| input.name(k).ends_with('A'))
+            .collect();
+        for (i, &d) in input.directions.iter().cycle().enumerate() {
+            for g in ghosts.iter_mut() {
+                *g = input.next(d, *g);
+            }
+            if ghosts.iter().all(|&g| input.name(g).ends_with('Z')) {
+                return i + 1;
+            }
+        }
+        unreachable!()
+    }
+
+    #[test]
+    fn test_p2_offsets() {
+        // 11A reaches Z at 3, 5, 7, ... and 22A at 1, 4, 7, ...
+        let input = "L
+
+11A = (11B, 11B)
+11B = (11C, 11C)
+11C = (11Z, 11Z)
+11Z = (11C, 11C)
+22A = (22Z, 22Z)
+22Z = (22B, 22B)
+22B = (22C, 22C)
+22C = (22Z, 22Z)";
+        let map = generate(input);
+
+        assert_eq!(
+            GhostCycle {
+                offset: 2,
+                length: 2,
+                pre_cycle_hits: vec![],
+                cycle_hits: vec![3],
+            },
+            ghost_cycle(&map, "11A")
+        );
+        assert_eq!(7, brute_force(&map));
+        assert_eq!(7, part2(&map));
+    }
+
+    #[test]
+    fn test_p2_pre_cycle() {
+        // 11A only passes a Z node once, before it gets stuck in a loop without one
+        let input = "LR
+
+11A = (11B, 11B)
+11B = (11Z, 11Z)
+11Z = (11C, 11C)
+11C = (11C, 11C)
+22A = (22Z, 22Z)
+22Z = (22Z, 22Z)";
+        let map = generate(input);
+
+        assert_eq!(2, brute_force(&map));
+        assert_eq!(2, part2(&map));
+    }
+
+    #[test]
+    fn test_p2_residues() {
+        // 11A hits twice per cycle of 4 (2, 4, 6, ...), 22A every 3 steps and 33A at 2, 7, 12, ...
+        let input = "L
+
+11A = (11B, 11B)
+11B = (11Z, 11Z)
+11Z = (11C, 11C)
+11C = (1BZ, 1BZ)
+1BZ = (11B, 11B)
+22A = (22B, 22B)
+22B = (22C, 22C)
+22C = (22Z, 22Z)
+22Z = (22B, 22B)
+33A = (33B, 33B)
+33B = (33Z, 33Z)
+33Z = (33C, 33C)
+33C = (33D, 33D)
+33D = (33E, 33E)
+33E = (33B, 33B)";
+        let map = generate(input);
+
+        assert_eq!(2, ghost_cycle(&map, "11A").cycle_hits.len());
+        assert_eq!(12, brute_force(&map));
+        assert_eq!(12, part2(&map));
+    }
+
+    #[test]
+    fn test_position_after() {
+        let input = "LLR
+
+AAA = (BBB, BBB)
+BBB = (AAA, ZZZ)
+ZZZ = (ZZZ, ZZZ)";
+        let map = generate(input);
+
+        assert_eq!(Some("AAA"), map.position_after("AAA", 0));
+        assert_eq!(Some("BBB"), map.position_after("AAA", 5));
+        assert_eq!(Some("ZZZ"), map.position_after("AAA", 6));
+        assert_eq!(
+            Some("ZZZ"),
+            map.position_after("AAA", 1_000_000_000_000_000_000)
+        );
+        assert_eq!(None, map.position_after("CCC", 1));
+
+        // 11B and 11Z swap every step, 22B, 22C and 22Z rotate every 3 steps
+        let input = "LR
+
+11A = (11B, XXX)
+11B = (XXX, 11Z)
+11Z = (11B, XXX)
+22A = (22B, XXX)
+22B = (22C, 22C)
+22C = (22Z, 22Z)
+22Z = (22B, 22B)
+XXX = (XXX, XXX)";
+        let map = generate(input);
+        let steps = 1_000_000_000_000_000_000;
+        let mut current = map.id("22A").unwrap();
+        for (i, &d) in map.directions.iter().cycle().take(1000).enumerate() {
+            current = map.next(d, current);
+            assert_eq!(
+                Some(map.name(current)),
+                map.position_after("22A", i as u64 + 1)
+            );
+        }
+        assert_eq!(Some("11Z"), map.position_after("11A", steps));
+        assert_eq!(Some("22B"), map.position_after("22A", steps));
+    }
+
+    #[test]
+    fn test_dot() {
+        let input = "LLR
+
+AAA = (BBB, BBB)
+BBB = (AAA, ZZZ)
+ZZZ = (ZZZ, ZZZ)";
+        let map = generate(input);
+
+        assert_eq!(
+            vec!["AAA", "BBB", "AAA", "BBB", "AAA", "BBB", "ZZZ"],
+            part1_path(&map)
+        );
+        assert_eq!(
+            "digraph network {
+    \"AAA\" [style=filled, fillcolor=palegreen];
+    \"AAA\" -> \"BBB\" [label=\"LR\", color=blue, penwidth=3];
+    \"BBB\" -> \"AAA\" [label=\"L\", color=blue, penwidth=3];
+    \"BBB\" -> \"ZZZ\" [label=\"R\", color=blue, penwidth=3];
+    \"ZZZ\" [style=filled, fillcolor=lightcoral];
+    \"ZZZ\" -> \"ZZZ\" [label=\"LR\"];
+}
+",
+            to_dot(&map, &[part1_path(&map)])
+        );
+    }
+
+    #[test]
+    fn test_ghost_paths() {
+        let input = "LR
+
+11A = (11B, XXX)
+11B = (XXX, 11Z)
+11Z = (11B, XXX)
+22A = (22B, XXX)
+22B = (22C, 22C)
+22C = (22Z, 22Z)
+22Z = (22B, 22B)
+XXX = (XXX, XXX)";
+        let map = generate(input);
+        let paths = ghost_paths(&map);
+
+        assert_eq!(vec!["11A", "11B", "11Z", "11B"], paths[0]);
+        assert_eq!(
+            vec!["22A", "22B", "22C", "22Z", "22B", "22C", "22Z", "22B"],
+            paths[1]
+        );
+        let dot = to_dot(&map, &paths);
+        assert!(dot.contains("\"11B\" -> \"11Z\" [label=\"R\", color=blue, penwidth=3];"));
+        assert!(dot.contains("\"22C\" -> \"22Z\" [label=\"LR\", color=red, penwidth=3];"));
+        assert!(dot.contains("\"XXX\" -> \"XXX\" [label=\"LR\"];"));
+    }
 }
@@ -1,3 +1,5 @@
+use std::fmt::Display;
+
 pub fn generate(input: &str) -> Vec<Vec<isize>> {
     input
         .lines()
@@ -5,30 +7,125 @@ pub fn generate(input: &str) -> Vec<Vec<isize>> {
         .collect()
 }
 
-fn differences(input: &[isize]) -> impl Iterator<Item = isize> + '_ {
-    input.windows(2).map(|s| s[1] - s[0])
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtrapolationError {
+    /// the differences never reach all zeros, so the degree is unknown
+    TooShort,
+    Overflow,
 }
 
-fn next(input: &[isize]) -> isize {
-    let d: Vec<_> = differences(input).collect();
-    if d.iter().all(|&n| n == 0) {
-        return input[0];
+impl Display for ExtrapolationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtrapolationError::TooShort => {
+                write!(f, "sequence is too short to determine its degree")
+            }
+            ExtrapolationError::Overflow => write!(f, "overflow while extrapolating"),
+        }
     }
-    input.last().unwrap() + next(&d)
 }
 
-pub fn part1(input: &[Vec<isize>]) -> isize {
-    input.iter().map(|seq| next(seq)).sum()
+fn differences(input: &[i128]) -> Result<Vec<i128>, ExtrapolationError> {
+    input
+        .windows(2)
+        .map(|s| s[1].checked_sub(s[0]).ok_or(ExtrapolationError::Overflow))
+        .collect()
+}
+
+/// The rows of differences of a sequence, ending in the first row of all zeros.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pyramid {
+    levels: Vec<Vec<i128>>,
+}
+
+impl Pyramid {
+    pub fn levels(&self) -> &[Vec<i128>] {
+        &self.levels
+    }
+
+    /// Degree of the polynomial behind the sequence, `None` if it's all zeros.
+    pub fn degree(&self) -> Option<usize> {
+        self.levels.len().checked_sub(2)
+    }
+
+    /// First element of every level above the zeros.
+    fn leading(&self) -> impl Iterator<Item = i128> + '_ {
+        self.levels[..self.levels.len() - 1].iter().map(|l| l[0])
+    }
+}
+
+/// Lays the levels out like the puzzle text, every row shifted by half a column.
+impl Display for Pyramid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let width = self
+            .levels
+            .iter()
+            .flatten()
+            .map(|n| n.to_string().len())
+            .max()
+            .unwrap_or(0)
+            + 2;
+        let width = width + width % 2;
+        for (i, level) in self.levels.iter().enumerate() {
+            let row: String = level.iter().map(|n| format!("{n:<width$}")).collect();
+            writeln!(
+                f,
+                "{:indent$}{}",
+                "",
+                row.trim_end(),
+                indent = i * width / 2
+            )?;
+        }
+        Ok(())
+    }
+}
+
+pub fn pyramid(input: &[isize]) -> Result<Pyramid, ExtrapolationError> {
+    let mut levels = vec![input.iter().map(|&n| n as i128).collect::<Vec<_>>()];
+    while !levels.last().unwrap().iter().all(|&n| n == 0) {
+        let next = differences(levels.last().unwrap())?;
+        levels.push(next);
+    }
+    if levels.last().unwrap().is_empty() {
+        return Err(ExtrapolationError::TooShort);
+    }
+    Ok(Pyramid { levels })
 }
-fn prev(input: &[isize]) -> isize {
-    let d: Vec<_> = differences(input).collect();
-    if d.iter().all(|&n| n == 0) {
-        return input[0];
+
+/// The value of the sequence's polynomial at index `at`, so `input.len()` is the
+/// next value and `-1` the one before the first. Uses Newton's forward formula
+/// `f(x) = sum(binomial(x, j) * d_j)` with the leading differences `d_j`.
+pub fn extrapolate(input: &[isize], at: i128) -> Result<i128, ExtrapolationError> {
+    let mut value: i128 = 0;
+    let mut binomial: i128 = 1;
+    for (j, d) in pyramid(input)?.leading().enumerate() {
+        let j = j as i128;
+        if j > 0 {
+            // binomial(x, j) = binomial(x, j - 1) * (x - j + 1) / j, always divides evenly
+            binomial = binomial
+                .checked_mul(at - j + 1)
+                .ok_or(ExtrapolationError::Overflow)?
+                / j;
+        }
+        value = binomial
+            .checked_mul(d)
+            .and_then(|t| value.checked_add(t))
+            .ok_or(ExtrapolationError::Overflow)?;
     }
-    input.first().unwrap() - prev(&d)
+    Ok(value)
 }
+
+pub fn part1(input: &[Vec<isize>]) -> isize {
+    let sum: i128 = input
+        .iter()
+        .map(|seq| extrapolate(seq, seq.len() as i128).unwrap())
+        .sum();
+    sum.try_into().unwrap()
+}
+
 pub fn part2(input: &[Vec<isize>]) -> isize {
-    input.iter().map(|seq| prev(seq)).sum()
+    let sum: i128 = input.iter().map(|seq| extrapolate(seq, -1).unwrap()).sum();
+    sum.try_into().unwrap()
 }
 
 #[cfg(test)]
@@ -51,4 +148,59 @@ mod test {
 
         assert_eq!(2, part2(&generate(input)));
     }
+
+    #[test]
+    fn test_extrapolate() {
+        let seq = [10, 13, 16, 21, 30, 45];
+        assert_eq!(Ok(68), extrapolate(&seq, 6));
+        assert_eq!(Ok(5), extrapolate(&seq, -1));
+        assert_eq!(Ok(10), extrapolate(&seq, 0));
+        assert_eq!(Ok(30), extrapolate(&seq, 4));
+
+        // n^3 - 2n
+        let cube: Vec<isize> = (0..6).map(|n| n * n * n - 2 * n).collect();
+        for at in [-1_000, -7, 10, 1_000_000] {
+            assert_eq!(Ok(at * at * at - 2 * at), extrapolate(&cube, at));
+        }
+        assert_eq!(
+            Err(ExtrapolationError::Overflow),
+            extrapolate(&cube, 1 << 50)
+        );
+
+        // the differences of a cubic only reach zero with at least 5 values
+        assert_eq!(
+            Err(ExtrapolationError::TooShort),
+            extrapolate(&cube[..4], 4)
+        );
+        assert_eq!(Ok(115), extrapolate(&cube[..5], 5));
+        assert_eq!(Err(ExtrapolationError::TooShort), extrapolate(&[], 0));
+        assert_eq!(Ok(0), extrapolate(&[0], 3));
+    }
+
+    #[test]
+    fn test_pyramid() {
+        let p = pyramid(&[1, 3, 6, 10, 15, 21]).unwrap();
+        assert_eq!(Some(2), p.degree());
+        assert_eq!(
+            vec![
+                vec![1, 3, 6, 10, 15, 21],
+                vec![2, 3, 4, 5, 6],
+                vec![1, 1, 1, 1],
+                vec![0, 0, 0],
+            ],
+            p.levels()
+        );
+        assert_eq!(
+            "1   3   6   10  15  21
+  2   3   4   5   6
+    1   1   1   1
+      0   0   0
+",
+            p.to_string()
+        );
+
+        assert_eq!(Some(0), pyramid(&[7, 7]).unwrap().degree());
+        assert_eq!(None, pyramid(&[0, 0]).unwrap().degree());
+        assert_eq!(Err(ExtrapolationError::TooShort), pyramid(&[1, 2]));
+    }
 }
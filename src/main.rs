@@ -1,26 +1,10 @@
-mod day1;
-mod day10;
-mod day11;
-mod day12;
-mod day13;
-mod day14;
-mod day15;
-mod day16;
-mod day2;
-mod day3;
-mod day4;
-mod day5;
-mod day6;
-mod day7;
-mod day8;
-mod day9;
-mod utils;
+use aoc2023::*;
 
 aoc_main::main! {
     year 2023;
     day1 => part1, part2;
     day2: generate => part1, part2;
-    day3 => part1, part2;
+    day3: generate => part1, part2;
     day4: generate => part1, part2;
     day5: generate => part1, part2;
     day6: generate => part1, part2;
@@ -1,4 +1,7 @@
-use std::ops::{Index, IndexMut};
+use std::{
+    fmt::Display,
+    ops::{Add, Index, IndexMut, Range, Sub},
+};
 
 #[derive(Debug, Clone)]
 pub struct Grid<T> {
@@ -23,6 +26,21 @@ impl<T> Grid<T> {
     pub fn height(&self) -> usize {
         self.height
     }
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.data.iter()
+    }
+}
+
+impl<T: Display> Display for Grid<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                write!(f, "{}", self[(x, y)])?
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
 }
 
 impl<T> Index<(usize, usize)> for Grid<T> {
@@ -40,3 +58,218 @@ impl<T> IndexMut<(usize, usize)> for Grid<T> {
         &mut self.data[x + y * self.width]
     }
 }
+
+/// A set of half open intervals, kept sorted and merged so that no two
+/// intervals overlap or touch.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IntervalSet<T> {
+    ranges: Vec<Range<T>>,
+}
+
+impl<T> Default for IntervalSet<T> {
+    fn default() -> Self {
+        Self { ranges: vec![] }
+    }
+}
+
+impl<T: Copy + Ord> IntervalSet<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, item: Range<T>) {
+        if item.is_empty() {
+            return;
+        }
+        let first = self.ranges.partition_point(|r| r.end < item.start);
+        let last = self.ranges.partition_point(|r| r.start <= item.end);
+        let merged = if first < last {
+            item.start.min(self.ranges[first].start)..item.end.max(self.ranges[last - 1].end)
+        } else {
+            item
+        };
+        self.ranges.splice(first..last, [merged]);
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let mut res = self.clone();
+        for r in other {
+            res.insert(r.clone());
+        }
+        res
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut ranges = vec![];
+        let (mut i, mut j) = (0, 0);
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (a, b) = (&self.ranges[i], &other.ranges[j]);
+            let start = a.start.max(b.start);
+            let end = a.end.min(b.end);
+            if start < end {
+                ranges.push(start..end);
+            }
+            if a.end < b.end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        Self { ranges }
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut ranges = vec![];
+        let mut j = 0;
+        for r in &self.ranges {
+            let mut start = r.start;
+            while j < other.ranges.len() && other.ranges[j].end <= start {
+                j += 1;
+            }
+            let mut k = j;
+            while k < other.ranges.len() && other.ranges[k].start < r.end {
+                if start < other.ranges[k].start {
+                    ranges.push(start..other.ranges[k].start);
+                }
+                start = start.max(other.ranges[k].end);
+                k += 1;
+            }
+            if start < r.end {
+                ranges.push(start..r.end);
+            }
+        }
+        Self { ranges }
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        let i = self.ranges.partition_point(|r| r.end <= value);
+        self.ranges.get(i).is_some_and(|r| r.start <= value)
+    }
+
+    pub fn contains_range(&self, item: &Range<T>) -> bool {
+        if item.is_empty() {
+            return true;
+        }
+        let i = self.ranges.partition_point(|r| r.end <= item.start);
+        self.ranges
+            .get(i)
+            .is_some_and(|r| r.start <= item.start && item.end <= r.end)
+    }
+
+    /// Cuts every interval at the given points, the pieces stay sorted.
+    pub fn split_at(&self, points: &[T]) -> Vec<Range<T>> {
+        let mut points = points.to_vec();
+        points.sort_unstable();
+        let mut pieces = vec![];
+        for r in &self.ranges {
+            let mut start = r.start;
+            let first = points.partition_point(|&p| p <= r.start);
+            for &p in points[first..].iter().take_while(|&&p| p < r.end) {
+                if start < p {
+                    pieces.push(start..p);
+                    start = p;
+                }
+            }
+            pieces.push(start..r.end);
+        }
+        pieces
+    }
+
+    pub fn min(&self) -> Option<T> {
+        self.ranges.first().map(|r| r.start)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Range<T>> {
+        self.ranges.iter()
+    }
+}
+
+impl<T: Copy + Ord + Default + Add<Output = T> + Sub<Output = T>> IntervalSet<T> {
+    /// Number of values in the set.
+    pub fn total_len(&self) -> T {
+        self.ranges
+            .iter()
+            .fold(T::default(), |acc, r| acc + (r.end - r.start))
+    }
+}
+
+impl<T: Copy + Ord> FromIterator<Range<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = Range<T>>>(iter: I) -> Self {
+        let mut set = Self::new();
+        for r in iter {
+            set.insert(r);
+        }
+        set
+    }
+}
+
+impl<'a, T> IntoIterator for &'a IntervalSet<T> {
+    type Item = &'a Range<T>;
+    type IntoIter = std::slice::Iter<'a, Range<T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.ranges.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn set(ranges: &[Range<i64>]) -> IntervalSet<i64> {
+        ranges.iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_insert() {
+        let s = set(&[5..7, 0..2, 10..12, 1..6, 20..21, 12..15]);
+        assert_eq!(
+            vec![0..7, 10..15, 20..21],
+            s.iter().cloned().collect::<Vec<_>>()
+        );
+
+        // covers several ranges but ends before the last one ends
+        let s = set(&[0..2, 4..6, 8..20, 1..10]);
+        assert_eq!(1, s.iter().count());
+        assert_eq!(Some(&(0..20)), s.iter().next());
+        assert_eq!(20, s.total_len());
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a = set(&[0..10, 20..30]);
+        let b = set(&[5..25, 28..40]);
+
+        let union = a.union(&b);
+        assert_eq!(1, union.iter().count());
+        assert!(union.contains_range(&(0..40)));
+        assert_eq!(40, union.total_len());
+        assert_eq!(set(&[5..10, 20..25, 28..30]), a.intersection(&b));
+        assert_eq!(set(&[0..5, 25..28]), a.difference(&b));
+        assert_eq!(set(&[10..20, 30..40]), b.difference(&a));
+    }
+
+    #[test]
+    fn test_contains() {
+        let s = set(&[-5..0, 3..8]);
+        assert!(s.contains(-5));
+        assert!(!s.contains(0));
+        assert!(s.contains(7));
+        assert!(!s.contains(8));
+        assert!(s.contains_range(&(4..8)));
+        assert!(!s.contains_range(&(-1..4)));
+    }
+
+    #[test]
+    fn test_split_at() {
+        let s = set(&[0..10, 20..30]);
+        assert_eq!(
+            vec![0..3, 3..10, 20..25, 25..30],
+            s.split_at(&[25, 3, 0, 10, 15])
+        );
+    }
+}